@@ -0,0 +1,273 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::{
+    error::Error,
+    input::{InputLayer, PhysicalKey},
+};
+
+/// A physical input that can drive an action or an axis.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    /// A key by the character it types. Prefer [`Binding::Physical`] except for
+    /// shortcuts named after a letter, such as Ctrl+S.
+    Key(VirtualKeyCode),
+    /// A key by its position on the keyboard, independent of the layout.
+    Physical(PhysicalKey),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+impl Binding {
+    /// `1.0` for a held key or button, the scrolled lines in the bound direction for scroll.
+    fn value(self, input: InputLayer<'_>) -> f32 {
+        let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+        match self {
+            Self::Key(key) => pressed(input.is_pressed(key)),
+            Self::Physical(key) => pressed(input.is_pressed_physical(key)),
+            Self::Mouse(button) => pressed(input.is_mouse_pressed(button)),
+            Self::ScrollUp => input.scroll_delta().y.max(0.0),
+            Self::ScrollDown => (-input.scroll_delta().y).max(0.0),
+            Self::ScrollLeft => (-input.scroll_delta().x).max(0.0),
+            Self::ScrollRight => input.scroll_delta().x.max(0.0),
+        }
+    }
+
+    fn is_just_pressed(self, input: InputLayer<'_>) -> bool {
+        match self {
+            Self::Key(key) => input.is_just_pressed(key),
+            Self::Physical(key) => input.is_just_pressed_physical(key),
+            Self::Mouse(button) => input.is_mouse_just_pressed(button),
+            _ => self.value(input) > 0.0,
+        }
+    }
+
+    fn is_just_released(self, input: InputLayer<'_>) -> bool {
+        match self {
+            Self::Key(key) => input.is_just_released(key),
+            Self::Physical(key) => input.is_just_released_physical(key),
+            Self::Mouse(button) => input.is_mouse_just_released(button),
+            _ => false,
+        }
+    }
+}
+
+fn sum(bindings: &[Binding], input: InputLayer<'_>) -> f32 {
+    bindings.iter().map(|binding| binding.value(input)).sum()
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(default)]
+    pub negative: Vec<Binding>,
+    #[serde(default)]
+    pub positive: Vec<Binding>,
+    /// Values with a smaller magnitude read as `0.0`.
+    #[serde(default)]
+    pub dead_zone: f32,
+}
+
+impl AxisBinding {
+    fn value(&self, input: InputLayer<'_>) -> f32 {
+        let value = sum(&self.positive, input) - sum(&self.negative, input);
+        if value.abs() <= self.dead_zone {
+            0.0
+        } else {
+            value
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Axis2Binding {
+    #[serde(default)]
+    pub up: Vec<Binding>,
+    #[serde(default)]
+    pub down: Vec<Binding>,
+    #[serde(default)]
+    pub left: Vec<Binding>,
+    #[serde(default)]
+    pub right: Vec<Binding>,
+    /// Vectors with a shorter length read as zero.
+    #[serde(default)]
+    pub dead_zone: f32,
+    /// Clamps the length to `1.0` so that diagonals are not faster than straight lines.
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+}
+
+fn default_normalize() -> bool {
+    true
+}
+
+impl Default for Axis2Binding {
+    fn default() -> Self {
+        Self {
+            up: vec![],
+            down: vec![],
+            left: vec![],
+            right: vec![],
+            dead_zone: 0.0,
+            normalize: true,
+        }
+    }
+}
+
+impl Axis2Binding {
+    /// Up, down, left and right on the given keys.
+    pub fn keys(
+        up: VirtualKeyCode,
+        down: VirtualKeyCode,
+        left: VirtualKeyCode,
+        right: VirtualKeyCode,
+    ) -> Self {
+        Self {
+            up: vec![Binding::Key(up)],
+            down: vec![Binding::Key(down)],
+            left: vec![Binding::Key(left)],
+            right: vec![Binding::Key(right)],
+            ..Default::default()
+        }
+    }
+
+    /// Up, down, left and right on keys at the given positions, such as
+    /// `physical(PhysicalKey::W, PhysicalKey::S, PhysicalKey::A, PhysicalKey::D)`.
+    pub fn physical(
+        up: PhysicalKey,
+        down: PhysicalKey,
+        left: PhysicalKey,
+        right: PhysicalKey,
+    ) -> Self {
+        Self {
+            up: vec![Binding::Physical(up)],
+            down: vec![Binding::Physical(down)],
+            left: vec![Binding::Physical(left)],
+            right: vec![Binding::Physical(right)],
+            ..Default::default()
+        }
+    }
+
+    fn value(&self, input: InputLayer<'_>) -> Vec2 {
+        let value = vec2(
+            sum(&self.right, input) - sum(&self.left, input),
+            sum(&self.up, input) - sum(&self.down, input),
+        );
+        if value.length() <= self.dead_zone {
+            Vec2::ZERO
+        } else if self.normalize {
+            value.clamp_length_max(1.0)
+        } else {
+            value
+        }
+    }
+}
+
+/// Named actions and axes bound to keys, mouse buttons and scroll.
+///
+/// Queries take either an [`Input`](crate::input::Input), which reads the base
+/// layer, or an [`InputLayer`].
+///
+/// Bindings can be loaded from and saved to RON, for example:
+///
+/// ```ron
+/// (
+///     actions: { "jump": [Key(Space)], "crouch": [Physical(LControl)] },
+///     axes2: { "move": (up: [Key(W)], down: [Key(S)], left: [Key(A)], right: [Key(D)]) },
+/// )
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    actions: HashMap<String, Vec<Binding>>,
+    #[serde(default)]
+    axes: HashMap<String, AxisBinding>,
+    #[serde(default)]
+    axes2: HashMap<String, Axis2Binding>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_ron(&fs::read_to_string(path).map_err(Error::Io)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        fs::write(path, self.to_ron()).map_err(Error::Io)
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self, Error> {
+        ron::from_str(s).map_err(Error::ParseBindings)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap()
+    }
+
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) {
+        self.actions.entry(action.into()).or_default().push(binding);
+    }
+
+    pub fn bind_axis(&mut self, axis: impl Into<String>, binding: AxisBinding) {
+        self.axes.insert(axis.into(), binding);
+    }
+
+    pub fn bind_axis2(&mut self, axis: impl Into<String>, binding: Axis2Binding) {
+        self.axes2.insert(axis.into(), binding);
+    }
+
+    /// Removes every binding of `action`.
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether any binding of `action` is held. Unknown actions are never pressed.
+    pub fn action<'a>(&self, input: impl Into<InputLayer<'a>>, action: &str) -> bool {
+        let input = input.into();
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.value(input) > 0.0)
+    }
+
+    pub fn action_just_pressed<'a>(&self, input: impl Into<InputLayer<'a>>, action: &str) -> bool {
+        let input = input.into();
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_pressed(input))
+    }
+
+    pub fn action_just_released<'a>(&self, input: impl Into<InputLayer<'a>>, action: &str) -> bool {
+        let input = input.into();
+        self.bindings(action)
+            .iter()
+            .any(|binding| binding.is_just_released(input))
+    }
+
+    /// Unknown axes read as `0.0`.
+    pub fn axis<'a>(&self, input: impl Into<InputLayer<'a>>, axis: &str) -> f32 {
+        let input = input.into();
+        self.axes
+            .get(axis)
+            .map_or(0.0, |binding| binding.value(input))
+    }
+
+    /// Unknown axes read as zero.
+    pub fn axis2<'a>(&self, input: impl Into<InputLayer<'a>>, axis: &str) -> Vec2 {
+        let input = input.into();
+        self.axes2
+            .get(axis)
+            .map_or(Vec2::ZERO, |binding| binding.value(input))
+    }
+}
@@ -0,0 +1,277 @@
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box containing `points`, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, p| {
+            Self::new(aabb.min.min(p), aabb.max.max(p))
+        }))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// A sphere around the center of the bounding box of `points`. Not the
+    /// smallest possible, but close for most meshes.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3> + Clone) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|p| p.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+        Some(Self::new(center, radius))
+    }
+
+    /// The sphere containing this one after `transform`, which may scale
+    /// non-uniformly.
+    pub fn transform(&self, transform: &Mat4) -> Self {
+        let scale = transform
+            .x_axis
+            .xyz()
+            .length_squared()
+            .max(transform.y_axis.xyz().length_squared())
+            .max(transform.z_axis.xyz().length_squared())
+            .sqrt();
+        Self::new(transform.transform_point3(self.center), self.radius * scale)
+    }
+}
+
+/// The volume a camera sees, as six planes facing inward.
+///
+/// Each plane is stored as `(normal, distance)`, so that `normal.dot(p) + distance`
+/// is the signed distance of `p`, positive inside.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view-projection matrix with a `0.0..=1.0` depth
+    /// range, as wgpu uses.
+    pub fn from_matrix(m: Mat4) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            // An infinite far plane has no normal and never culls anything.
+            let length = plane.xyz().length();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+
+    /// Left, right, bottom, top, and the two depth planes.
+    pub fn planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    /// False only if the sphere is entirely outside. Spheres near the corners may
+    /// pass while being outside.
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let radius = half_extents.dot(plane.xyz().abs());
+            plane.xyz().dot(center) + plane.w >= -radius
+        })
+    }
+}
+
+/// A half-line from `origin` along `direction`. Hits are reported as the `t` of
+/// [`Ray::at`], which is a distance when `direction` is normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /// The ray in the space that `transform` maps to, keeping `t` unchanged.
+    pub fn transform(&self, transform: &Mat4) -> Self {
+        Self::new(
+            transform.transform_point3(self.origin),
+            transform.transform_vector3(self.direction),
+        )
+    }
+
+    /// The first `t` at which the ray is inside the sphere, `0.0` if it starts inside.
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+        let a = self.direction.length_squared();
+        let b = offset.dot(self.direction);
+        let c = offset.length_squared() - sphere.radius * sphere.radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let far = (-b + discriminant.sqrt()) / a;
+        (far >= 0.0).then(|| ((-b - discriminant.sqrt()) / a).max(0.0))
+    }
+
+    /// Möller–Trumbore intersection with the triangle `a`, `b`, `c`, hitting
+    /// both faces.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let ab = b - a;
+        let ac = c - a;
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        // Relative to the sizes involved, so that small triangles and unnormalized
+        // directions still hit, while edge-on rays and degenerate triangles miss.
+        if det.abs() <= f32::EPSILON * ab.length() * ac.length() * self.direction.length() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let offset = self.origin - a;
+        let u = offset.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = offset.cross(ab);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = ac.dot(q) * inv_det;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Mat4};
+
+    use super::*;
+
+    fn triangle(scale: f32) -> [Vec3; 3] {
+        [
+            vec3(-1.0, -1.0, 5.0),
+            vec3(1.0, -1.0, 5.0),
+            vec3(0.0, 1.0, 5.0),
+        ]
+        .map(|p| vec3(p.x * scale, p.y * scale, p.z))
+    }
+
+    fn forward() -> Ray {
+        Ray::new(Vec3::ZERO, Vec3::Z)
+    }
+
+    #[test]
+    fn ray_hits_triangle() {
+        let [a, b, c] = triangle(1.0);
+        assert_eq!(forward().intersect_triangle(a, b, c), Some(5.0));
+        assert_eq!(forward().intersect_triangle(a, c, b), Some(5.0));
+    }
+
+    #[test]
+    fn ray_hits_tiny_triangle() {
+        let [a, b, c] = triangle(1e-4);
+        assert_eq!(forward().intersect_triangle(a, b, c), Some(5.0));
+        let slow = Ray::new(Vec3::ZERO, Vec3::Z * 1e-3);
+        let t = slow.intersect_triangle(a, b, c).unwrap();
+        assert!((t - 5000.0).abs() < 1e-2, "{t}");
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let [a, b, c] = triangle(1.0);
+        let beside = Ray::new(vec3(2.0, 0.0, 0.0), Vec3::Z);
+        assert_eq!(beside.intersect_triangle(a, b, c), None);
+        let away = Ray::new(Vec3::ZERO, -Vec3::Z);
+        assert_eq!(away.intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn edge_on_ray_misses_triangle() {
+        let [a, b, c] = triangle(1.0);
+        let edge_on = Ray::new(vec3(-5.0, 0.0, 5.0), Vec3::X);
+        assert_eq!(edge_on.intersect_triangle(a, b, c), None);
+        assert_eq!(forward().intersect_triangle(a, a, c), None);
+    }
+
+    #[test]
+    fn ray_hits_sphere() {
+        let sphere = Sphere::new(vec3(0.0, 0.0, 5.0), 1.0);
+        assert_eq!(forward().intersect_sphere(&sphere), Some(4.0));
+        let inside = Ray::new(sphere.center, Vec3::X);
+        assert_eq!(inside.intersect_sphere(&sphere), Some(0.0));
+        let beside = Ray::new(vec3(2.0, 0.0, 0.0), Vec3::Z);
+        assert_eq!(beside.intersect_sphere(&sphere), None);
+    }
+
+    fn frustum() -> Frustum {
+        Frustum::from_matrix(Mat4::perspective_lh(1.0, 1.0, 0.1, 100.0))
+    }
+
+    #[test]
+    fn frustum_keeps_visible_bounds() {
+        let frustum = frustum();
+        assert!(frustum.intersects_sphere(&Sphere::new(vec3(0.0, 0.0, 5.0), 1.0)));
+        // Straddling the near plane.
+        assert!(frustum.intersects_sphere(&Sphere::new(Vec3::ZERO, 1.0)));
+        assert!(frustum.intersects_aabb(&Aabb::new(vec3(-1.0, -1.0, 4.0), vec3(1.0, 1.0, 6.0))));
+        assert!(frustum.intersects_aabb(&Aabb::new(vec3(-1.0, -1.0, 99.0), vec3(1.0, 1.0, 101.0))));
+    }
+
+    #[test]
+    fn frustum_culls_hidden_bounds() {
+        let frustum = frustum();
+        assert!(!frustum.intersects_sphere(&Sphere::new(vec3(0.0, 0.0, -5.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(vec3(50.0, 0.0, 5.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(vec3(0.0, 0.0, 200.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&Aabb::new(vec3(-1.0, 10.0, 4.0), vec3(1.0, 12.0, 6.0))));
+    }
+
+    #[test]
+    fn infinite_far_plane_culls_nothing_behind_it() {
+        let frustum = Frustum::from_matrix(Mat4::perspective_infinite_reverse_lh(1.0, 1.0, 0.1));
+        assert!(frustum.intersects_sphere(&Sphere::new(vec3(0.0, 0.0, 1e6), 1.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(vec3(0.0, 0.0, -5.0), 1.0)));
+    }
+}
@@ -1,16 +1,168 @@
-use std::mem;
+use std::{cell::Cell, f32::consts::FRAC_PI_2, mem, rc::Rc};
 
-use glam::{Affine3A, Mat4};
+use glam::{Affine3A, EulerRot, Mat3A, Mat4, Quat, Vec2, Vec3, Vec3A};
+use winit::event::MouseButton;
 
-use crate::core::Context;
+use crate::{
+    action::{ActionMap, Axis2Binding, AxisBinding, Binding},
+    bounds::{Frustum, Ray},
+    core::{Context, Texture},
+    input::{InputLayer, PhysicalKey},
+};
+
+/// WGSL declaration of the camera uniform at group 0, binding 0, to prepend to
+/// shaders drawn with a [`Camera`].
+pub const CAMERA_WGSL: &str = include_str!("shader/camera.wgsl");
+
+/// The uniform bound for shaders, laid out as the `Camera` struct in [`CAMERA_WGSL`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+    pub view_proj: [[f32; 4]; 4],
+    pub inv_view: [[f32; 4]; 4],
+    pub inv_proj: [[f32; 4]; 4],
+    pub inv_view_proj: [[f32; 4]; 4],
+    /// Eye position in world space.
+    pub position: [f32; 3],
+    /// [`Time::elapsed_secs`](crate::time::Time::elapsed_secs) at the last
+    /// [`Camera::update_buffer`].
+    pub time: f32,
+    /// `x`, `y`, `width` and `height` of the viewport in pixels of the target.
+    pub viewport: [f32; 4],
+    pub near: f32,
+    /// Infinite for [`DepthMode::ReverseZInfinite`] perspective projections.
+    pub far: f32,
+    pub _padding: [f32; 2],
+}
+
+// WGSL pads the struct to a multiple of 16 bytes, so the Rust side must match.
+const _: () = assert!(mem::size_of::<CameraUniform>() == 432);
+
+/// A rectangle of a render target in `0.0..=1.0` coordinates from its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl Viewport {
+    pub const FULL: Self = Self::new(0.0, 0.0, 1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The viewport of player `index` out of `count` sharing a screen: side by
+    /// side for two, quadrants for three or four.
+    pub fn split(count: usize, index: usize) -> Self {
+        match count {
+            0 | 1 => Self::FULL,
+            2 => Self::new(index as f32 * 0.5, 0.0, 0.5, 1.0),
+            _ => Self::new((index % 2) as f32 * 0.5, (index / 2) as f32 * 0.5, 0.5, 0.5),
+        }
+    }
+
+    /// `x`, `y`, `width` and `height` in pixels of a `width` by `height` target,
+    /// clamped to the target. Parts outside of it are cut off rather than
+    /// squeezed in, so the size can be smaller than the viewport asks for.
+    pub fn to_pixels(&self, width: u32, height: u32) -> [f32; 4] {
+        let (width, height) = (width as f32, height as f32);
+        let x0 = (self.x * width).clamp(0.0, width);
+        let y0 = (self.y * height).clamp(0.0, height);
+        let x1 = ((self.x + self.width) * width).clamp(x0, width);
+        let y1 = ((self.y + self.height) * height).clamp(y0, height);
+        [x0, y0, x1 - x0, y1 - y0]
+    }
+
+    /// The pixels covered by [`Viewport::to_pixels`] as a scissor rectangle.
+    pub fn scissor(&self, width: u32, height: u32) -> [u32; 4] {
+        let [x, y, w, h] = self.to_pixels(width, height);
+        let [x0, y0, x1, y1] = [x, y, x + w, y + h].map(|c| c.round() as u32);
+        [x0, y0, x1 - x0, y1 - y0]
+    }
+}
+
+/// A depth attachment, sized to a whole render target and shared by the cameras
+/// drawing to viewports of it.
+///
+/// It is cleared once per frame by the first draw, so cameras sharing it must use
+/// the same [`DepthMode`] and should not overlap; a picture-in-picture camera
+/// needs a buffer of its own.
+pub struct DepthBuffer {
+    texture: Texture,
+    /// Render frame of the last clear, and the depth mode it cleared for.
+    cleared: Cell<Option<(u64, DepthMode)>>,
+}
+
+impl DepthBuffer {
+    pub fn new(ctx: &Context, width: u32, height: u32) -> Self {
+        Self {
+            texture: ctx.create_texture_with_usage(
+                width,
+                height,
+                wgpu::TextureFormat::Depth32Float,
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            ),
+            cleared: Cell::new(None),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.texture.size()
+    }
+
+    /// The `Depth32Float` texture, bound with [`Context::depth_bind_group_layout`]
+    /// so that post effects can sample the depth of every viewport once the
+    /// cameras have drawn.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// How a draw by `camera` in render frame `frame` should load the depth.
+    pub(crate) fn load_op(&self, frame: u64, camera: &Camera) -> wgpu::LoadOp<f32> {
+        match self.cleared.get() {
+            Some((cleared, depth_mode)) if cleared == frame => {
+                debug_assert_eq!(
+                    depth_mode, camera.depth_mode,
+                    "cameras sharing a depth buffer must use the same depth mode"
+                );
+                wgpu::LoadOp::Load
+            }
+            _ => {
+                self.cleared.set(Some((frame, camera.depth_mode)));
+                wgpu::LoadOp::Clear(camera.depth_mode.clear_value())
+            }
+        }
+    }
+}
 
 pub struct Camera {
     pub transform: Affine3A,
     pub projection: Projection,
+    /// Renderers pick their depth test and clear value from it. Call
+    /// [`Camera::update_buffer`] after changing it.
+    pub depth_mode: DepthMode,
     buffer: wgpu::Buffer,
     pub(crate) bind_group: wgpu::BindGroup,
-    // depth_texture: wgpu::Texture,
-    pub(crate) depth_texture_view: wgpu::TextureView,
+    depth: Rc<DepthBuffer>,
+    viewport: Viewport,
 }
 
 impl Camera {
@@ -23,7 +175,7 @@ impl Camera {
     ) -> Self {
         let buffer = ctx.device().create_buffer(&wgpu::BufferDescriptor {
             label: Some("camera"),
-            size: mem::size_of::<[f32; 16]>() as _,
+            size: mem::size_of::<CameraUniform>() as _,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -35,43 +187,125 @@ impl Camera {
                 resource: buffer.as_entire_binding(),
             }],
         });
-        let depth_texture = ctx.device().create_texture(&wgpu::TextureDescriptor {
-            label: Some("depth"),
-            size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-        let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let res = Self {
             transform,
             projection,
+            depth_mode: DepthMode::default(),
             buffer,
             bind_group,
-            // depth_texture,
-            depth_texture_view,
+            depth: Rc::new(DepthBuffer::new(ctx, width, height)),
+            viewport: Viewport::FULL,
         };
         res.update_buffer(ctx);
         res
     }
 
+    /// Rebuilds the depth buffer for a new target size and fits the projection
+    /// to the viewport with [`Projection::set_viewport`]. Cameras sharing the depth
+    /// buffer have to be given the new one with [`Camera::set_depth_buffer`].
+    pub fn resize(&mut self, ctx: &Context, width: u32, height: u32) {
+        self.set_depth_buffer(ctx, Rc::new(DepthBuffer::new(ctx, width, height)));
+    }
+
+    /// Size of the target the camera renders to.
+    pub fn size(&self) -> (u32, u32) {
+        self.depth.size()
+    }
+
+    pub fn depth_buffer(&self) -> &Rc<DepthBuffer> {
+        &self.depth
+    }
+
+    pub fn depth_texture(&self) -> &Texture {
+        self.depth.texture()
+    }
+
+    /// Draws with `depth`, usually the depth buffer of another camera rendering to
+    /// the same target, whose size it takes.
+    pub fn set_depth_buffer(&mut self, ctx: &Context, depth: Rc<DepthBuffer>) {
+        self.depth = depth;
+        self.fit_projection(ctx);
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Restricts drawing to `viewport` of the target and fits the projection to it.
+    pub fn set_viewport(&mut self, ctx: &Context, viewport: Viewport) {
+        self.viewport = viewport;
+        self.fit_projection(ctx);
+    }
+
+    /// The viewport in pixels of the target, as `x`, `y`, `width` and `height`.
+    pub fn viewport_pixels(&self) -> [f32; 4] {
+        let (width, height) = self.size();
+        self.viewport.to_pixels(width, height)
+    }
+
+    fn fit_projection(&mut self, ctx: &Context) {
+        let [_, _, width, height] = self.viewport_pixels();
+        self.projection
+            .set_viewport(width.round() as u32, height.round() as u32);
+        self.update_buffer(ctx);
+    }
+
+    /// Uploads the uniform. Call it every frame when shaders read `time`.
     pub fn update_buffer(&self, ctx: &Context) {
-        ctx.queue().write_buffer(
-            &self.buffer,
-            0,
-            bytemuck::bytes_of(&self.matrix().to_cols_array()),
-        );
+        ctx.queue()
+            .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform(ctx)));
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection.matrix(self.depth_mode) * self.transform.inverse()
+    }
+
+    /// The planes of the volume the camera sees, in world space.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.view_projection())
     }
 
-    fn matrix(&self) -> Mat4 {
-        self.projection.matrix() * self.transform.inverse()
+    /// The world-space ray through a pixel of a `screen` sized target, such as
+    /// [`Input::cursor_position`](crate::input::Input::cursor_position) in the
+    /// window. Takes the viewport into account, and starts on the near plane with
+    /// a normalized direction.
+    pub fn screen_ray(&self, px: f32, py: f32, screen: Vec2) -> Ray {
+        let (width, height) = self.size();
+        let target = Vec2::new(width as f32, height as f32);
+        let [x, y, w, h] = self.viewport_pixels();
+        let uv = (Vec2::new(px, py) / screen.max(Vec2::ONE) * target - Vec2::new(x, y))
+            / Vec2::new(w, h).max(Vec2::splat(f32::EPSILON));
+        let ndc = uv * Vec2::new(2.0, -2.0) + Vec2::new(-1.0, 1.0);
+        let inverse = self.view_projection().inverse();
+        // The far plane may be at infinity, so the direction comes from a depth between.
+        let near = match self.depth_mode {
+            DepthMode::Standard => 0.0,
+            DepthMode::ReverseZInfinite => 1.0,
+        };
+        let origin = inverse.project_point3(ndc.extend(near));
+        let middle = inverse.project_point3(ndc.extend(0.5));
+        Ray::new(origin, (middle - origin).normalize())
+    }
+
+    pub fn uniform(&self, ctx: &Context) -> CameraUniform {
+        let view = Mat4::from(self.transform.inverse());
+        let proj = self.projection.matrix(self.depth_mode);
+        let view_proj = proj * view;
+        let (near, far) = self.projection.planes(self.depth_mode);
+        CameraUniform {
+            view: view.to_cols_array_2d(),
+            proj: proj.to_cols_array_2d(),
+            view_proj: view_proj.to_cols_array_2d(),
+            inv_view: Mat4::from(self.transform).to_cols_array_2d(),
+            inv_proj: proj.inverse().to_cols_array_2d(),
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            position: self.transform.translation.into(),
+            time: ctx.time().elapsed_secs(),
+            viewport: self.viewport_pixels(),
+            near,
+            far,
+            _padding: [0.0; 2],
+        }
     }
 
     pub(crate) fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
@@ -79,7 +313,7 @@ impl Camera {
             label: Some("camera"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -91,6 +325,34 @@ impl Camera {
     }
 }
 
+/// How depth values are distributed between the near and far planes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Depth `0.0` at the near plane and `1.0` at the far plane.
+    #[default]
+    Standard,
+    /// Depth `1.0` at the near plane, approaching `0.0` at infinity. Perspective
+    /// projections ignore their far plane. Floating-point depth keeps its precision
+    /// far better in the distance this way.
+    ReverseZInfinite,
+}
+
+impl DepthMode {
+    pub fn compare_function(self) -> wgpu::CompareFunction {
+        match self {
+            Self::Standard => wgpu::CompareFunction::LessEqual,
+            Self::ReverseZInfinite => wgpu::CompareFunction::GreaterEqual,
+        }
+    }
+    /// The depth of a pixel that nothing was drawn to.
+    pub fn clear_value(self) -> f32 {
+        match self {
+            Self::Standard => 1.0,
+            Self::ReverseZInfinite => 0.0,
+        }
+    }
+}
+
 pub enum Projection {
     Orthographic {
         left: f32,
@@ -100,6 +362,13 @@ pub enum Projection {
         near: f32,
         far: f32,
     },
+    /// One unit per pixel with the origin at the top-left corner and `y` pointing down.
+    Pixels {
+        width: f32,
+        height: f32,
+        near: f32,
+        far: f32,
+    },
     Perspective {
         fov_y_radians: f32,
         aspect_ratio: f32,
@@ -109,6 +378,25 @@ pub enum Projection {
 }
 
 impl Projection {
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        Self::Orthographic {
+            left,
+            right,
+            bottom,
+            top,
+            near,
+            far,
+        }
+    }
+    /// A 2D projection in pixels of a `width` by `height` target, with depths in `0.0..=1.0`.
+    pub fn pixels(width: u32, height: u32) -> Self {
+        Self::Pixels {
+            width: width as f32,
+            height: height as f32,
+            near: 0.0,
+            far: 1.0,
+        }
+    }
     pub fn perspective(fov_y_radians: f32, aspect_ratio: f32, z_near: f32, z_far: f32) -> Self {
         Self::Perspective {
             fov_y_radians,
@@ -117,7 +405,50 @@ impl Projection {
             z_far,
         }
     }
-    fn matrix(&self) -> Mat4 {
+    /// Fits the projection to a new target size. A perspective projection takes
+    /// its aspect ratio, an orthographic one keeps its center and vertical extent
+    /// and widens or narrows horizontally, and a pixel one covers the new size.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        match self {
+            Self::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                ..
+            } => {
+                let center = (*left + *right) / 2.0;
+                let half_width = (*top - *bottom).abs() * width / height / 2.0;
+                let sign = if *right < *left { -1.0 } else { 1.0 };
+                *left = center - sign * half_width;
+                *right = center + sign * half_width;
+            }
+            Self::Pixels {
+                width: w,
+                height: h,
+                ..
+            } => {
+                *w = width;
+                *h = height;
+            }
+            Self::Perspective { aspect_ratio, .. } => *aspect_ratio = width / height,
+        }
+    }
+    /// Distances to the near and far planes along the view direction.
+    fn planes(&self, depth_mode: DepthMode) -> (f32, f32) {
+        match *self {
+            Self::Orthographic { near, far, .. } | Self::Pixels { near, far, .. } => (near, far),
+            Self::Perspective { z_near, .. } if depth_mode == DepthMode::ReverseZInfinite => {
+                (z_near, f32::INFINITY)
+            }
+            Self::Perspective { z_near, z_far, .. } => (z_near, z_far),
+        }
+    }
+    fn matrix(&self, depth_mode: DepthMode) -> Mat4 {
+        let reverse = depth_mode == DepthMode::ReverseZInfinite;
+        // Orthographic depth is linear, so swapping the planes is enough to reverse it.
+        let planes = |near, far| if reverse { (far, near) } else { (near, far) };
         match *self {
             Self::Orthographic {
                 left,
@@ -126,7 +457,27 @@ impl Projection {
                 top,
                 near,
                 far,
-            } => Mat4::orthographic_lh(left, right, bottom, top, near, far),
+            } => {
+                let (near, far) = planes(near, far);
+                Mat4::orthographic_lh(left, right, bottom, top, near, far)
+            }
+            Self::Pixels {
+                width,
+                height,
+                near,
+                far,
+            } => {
+                let (near, far) = planes(near, far);
+                Mat4::orthographic_lh(0.0, width, height, 0.0, near, far)
+            }
+            Self::Perspective {
+                fov_y_radians,
+                aspect_ratio,
+                z_near,
+                ..
+            } if reverse => {
+                Mat4::perspective_infinite_reverse_lh(fov_y_radians, aspect_ratio, z_near)
+            }
             Self::Perspective {
                 fov_y_radians,
                 aspect_ratio,
@@ -136,3 +487,196 @@ impl Projection {
         }
     }
 }
+
+/// Keeps the pitch short of straight up or down, where yaw would flip.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Rotation looking toward `+z` turned right by `yaw` and down by `pitch`.
+fn look_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0)
+}
+
+/// First-person camera flying with the axes and actions named by its constants in
+/// an [`ActionMap`], looking with the mouse. [`FlyController::bind_defaults`]
+/// binds WASD, Q and E for down and up, by position so that they stay in place
+/// on any layout.
+pub struct FlyController {
+    /// Radians, positive turning right.
+    pub yaw: f32,
+    /// Radians, positive looking down.
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    /// Speed multiplier while [`FlyController::FAST`] is held.
+    pub fast_multiplier: f32,
+    /// Speed multiplier while [`FlyController::SLOW`] is held.
+    pub slow_multiplier: f32,
+    /// Radians per pixel of mouse motion.
+    pub sensitivity: f32,
+    /// Only look around while this button is held. Set to `None` when the cursor
+    /// is grabbed.
+    pub look_button: Option<MouseButton>,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 4.0,
+            fast_multiplier: 4.0,
+            slow_multiplier: 0.25,
+            sensitivity: 0.003,
+            look_button: Some(MouseButton::Right),
+        }
+    }
+}
+
+impl FlyController {
+    /// Two-dimensional axis moving forward and sideways.
+    pub const MOVE: &'static str = "move";
+    /// Axis moving up and down.
+    pub const MOVE_VERTICAL: &'static str = "move_vertical";
+    pub const FAST: &'static str = "move_fast";
+    pub const SLOW: &'static str = "move_slow";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds WASD, Q and E, left shift for fast and left control for slow.
+    pub fn bind_defaults(actions: &mut ActionMap) {
+        actions.bind_axis2(
+            Self::MOVE,
+            Axis2Binding::physical(
+                PhysicalKey::W,
+                PhysicalKey::S,
+                PhysicalKey::A,
+                PhysicalKey::D,
+            ),
+        );
+        actions.bind_axis(
+            Self::MOVE_VERTICAL,
+            AxisBinding {
+                negative: vec![Binding::Physical(PhysicalKey::Q)],
+                positive: vec![Binding::Physical(PhysicalKey::E)],
+                ..Default::default()
+            },
+        );
+        actions.bind(Self::FAST, Binding::Physical(PhysicalKey::LSHIFT));
+        actions.bind(Self::SLOW, Binding::Physical(PhysicalKey::LCONTROL));
+    }
+
+    pub fn update<'a>(
+        &mut self,
+        ctx: &Context,
+        input: impl Into<InputLayer<'a>>,
+        actions: &ActionMap,
+        camera: &mut Camera,
+    ) {
+        let input = input.into();
+        if self
+            .look_button
+            .is_none_or(|button| input.is_mouse_pressed(button))
+        {
+            let delta = input.mouse_delta() * self.sensitivity;
+            self.yaw += delta.x;
+            self.pitch = (self.pitch + delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        let rotation = look_rotation(self.yaw, self.pitch);
+
+        let planar = actions.axis2(input, Self::MOVE);
+        let movement = rotation * Vec3::X * planar.x
+            + rotation * Vec3::Z * planar.y
+            + Vec3::Y * actions.axis(input, Self::MOVE_VERTICAL);
+        let mut speed = self.speed;
+        if actions.action(input, Self::FAST) {
+            speed *= self.fast_multiplier;
+        }
+        if actions.action(input, Self::SLOW) {
+            speed *= self.slow_multiplier;
+        }
+
+        camera.transform.matrix3 = Mat3A::from_quat(rotation);
+        camera.transform.translation +=
+            Vec3A::from(movement.clamp_length_max(1.0) * speed * ctx.time().delta_secs());
+        camera.update_buffer(ctx);
+    }
+}
+
+/// Camera circling a target point: drag to orbit, drag with another button to
+/// pan and scroll to zoom.
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    /// Radians, positive turning right.
+    pub yaw: f32,
+    /// Radians, positive looking down.
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Radians per pixel of mouse motion.
+    pub sensitivity: f32,
+    /// Fraction of the distance panned per pixel of mouse motion.
+    pub pan_speed: f32,
+    /// Fraction of the distance zoomed per line scrolled.
+    pub zoom_speed: f32,
+    pub orbit_button: MouseButton,
+    pub pan_button: MouseButton,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 10.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            sensitivity: 0.005,
+            pan_speed: 0.002,
+            zoom_speed: 0.1,
+            orbit_button: MouseButton::Left,
+            pan_button: MouseButton::Middle,
+        }
+    }
+}
+
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            ..Default::default()
+        }
+    }
+
+    pub fn update<'a>(
+        &mut self,
+        ctx: &Context,
+        input: impl Into<InputLayer<'a>>,
+        camera: &mut Camera,
+    ) {
+        let input = input.into();
+        let delta = input.mouse_delta();
+        if input.is_mouse_pressed(self.orbit_button) {
+            self.yaw += delta.x * self.sensitivity;
+            self.pitch = (self.pitch + delta.y * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        let rotation = look_rotation(self.yaw, self.pitch);
+        if input.is_mouse_pressed(self.pan_button) {
+            // Moves the target so that the scene follows the cursor.
+            let pan = rotation * Vec3::new(-delta.x, delta.y, 0.0);
+            self.target += pan * self.pan_speed * self.distance;
+        }
+        self.distance = (self.distance * (-input.scroll_delta().y * self.zoom_speed).exp())
+            .clamp(self.min_distance, self.max_distance);
+
+        camera.transform = Affine3A::from_rotation_translation(
+            rotation,
+            self.target - rotation * Vec3::Z * self.distance,
+        );
+        camera.update_buffer(ctx);
+    }
+}
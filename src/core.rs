@@ -1,15 +1,62 @@
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    collections::HashMap,
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use glam::vec2;
 use winit::{
-    event::{ElementState, Event, WindowEvent},
+    dpi::{LogicalSize, PhysicalPosition},
+    error::ExternalError,
+    event::{DeviceEvent, ElementState, Event, Ime, MouseScrollDelta, VirtualKeyCode, WindowEvent},
     event_loop::EventLoop,
-    window::{Window, WindowBuilder},
+    window::{CursorGrabMode, Fullscreen, Window, WindowBuilder},
 };
 
-use crate::{camera::Camera, input::Input};
+use crate::{
+    camera::{Camera, Viewport},
+    error::Error,
+    filter::MagFilter,
+    image::{self, Image, Tonemap},
+    input::{Input, InputEvent, PhysicalKey},
+    pool::{PooledTexture, RenderTargetDesc, RenderTargetPool},
+    record::{InputPlayback, InputRecording, InputSource},
+    time::Time,
+};
+
+/// Pixels of a touchpad scroll that count as one line of a mouse wheel.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Events that a replayed [`InputRecording`] stands in for.
+fn is_input_event(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput { .. }
+            | WindowEvent::ModifiersChanged(_)
+            | WindowEvent::ReceivedCharacter(_)
+            | WindowEvent::Ime(_)
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::CursorEntered { .. }
+            | WindowEvent::CursorLeft { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::TouchpadMagnify { .. }
+            | WindowEvent::SmartMagnify { .. }
+            | WindowEvent::TouchpadRotate { .. }
+            | WindowEvent::TouchpadPressure { .. }
+            | WindowEvent::AxisMotion { .. }
+            | WindowEvent::Touch(_)
+    )
+}
 
 pub struct Engine {
     event_loop: EventLoop<()>,
-    window: Window,
     ctx: Context,
+    screenshot_key: Option<VirtualKeyCode>,
+    input_source: InputSource,
 }
 
 pub struct Context {
@@ -17,77 +64,333 @@ pub struct Context {
     adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface: wgpu::Surface,
+    surface: Option<wgpu::Surface>,
     config: wgpu::SurfaceConfiguration,
+    offscreen: Option<Arc<wgpu::Texture>>,
 
     camera_bind_group_layout: wgpu::BindGroupLayout,
     texture_bind_group_layout: wgpu::BindGroupLayout,
+    depth_bind_group_layout: wgpu::BindGroupLayout,
 
     input: Input,
-    frame_count: u64,
+    time: Time,
+    tick_count: u64,
+    screenshot: RefCell<Option<PathBuf>>,
+    screenshot_blit: OnceCell<MagFilter>,
+    clear_pipelines: RefCell<HashMap<wgpu::TextureFormat, Rc<wgpu::RenderPipeline>>>,
+    render_targets: RenderTargetPool,
+    render_frame: Cell<u64>,
+
+    // Declared last so that it outlives `surface`.
+    window: Option<Window>,
 }
 
 impl Engine {
     pub fn new() -> Self {
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new()
-            .with_visible(false)
-            .build(&event_loop)
-            .unwrap();
+        Self::builder().build()
+    }
 
-        let ctx = Context::new(&window);
+    pub fn try_new() -> Result<Self, Error> {
+        Self::builder().try_build()
+    }
 
-        Self {
-            event_loop,
-            window,
-            ctx,
-        }
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
     }
 
     pub fn context(&self) -> &Context {
         &self.ctx
     }
 
+    /// Saves the next presented frame as `screenshot-<frame>.png` when `key` is pressed.
+    pub fn set_screenshot_key(&mut self, key: Option<VirtualKeyCode>) {
+        self.screenshot_key = key;
+    }
+
+    /// Runs `App::update` at a fixed rate of one call per `step`, as many times per
+    /// frame as needed. `None` runs it once per frame with the measured frame delta.
+    ///
+    /// Panics if `step` is zero.
+    pub fn set_fixed_timestep(&mut self, step: Option<Duration>) {
+        self.ctx.time.set_fixed_timestep(step);
+    }
+
+    /// Records the input of every update and saves it to `path` as RON on exit.
+    pub fn record_input(&mut self, path: impl Into<PathBuf>) {
+        self.input_source = InputSource::record(path.into());
+    }
+
+    /// Replaces window input with `recording`, one recorded tick per update, and
+    /// simulates the recorded deltas. Live input resumes once it is exhausted.
+    pub fn replay_input(&mut self, recording: InputRecording) {
+        self.input_source = InputSource::Replay(InputPlayback::new(recording));
+    }
+
     pub fn run<A: App + 'static>(self, mut app: A) {
         let Self {
-            window,
             event_loop,
             mut ctx,
+            screenshot_key,
+            mut input_source,
         } = self;
 
-        window.set_visible(true);
+        ctx.window().unwrap().set_visible(true);
+        app.init(&ctx);
 
         event_loop.run(move |event, _, control_flow| match event {
             Event::MainEventsCleared => {
-                app.update(&ctx);
-                ctx.input.next_tick();
-                ctx.frame_count += 1;
-                window.request_redraw();
+                for _ in 0..ctx.time.begin_frame() {
+                    input_source.update(&mut ctx, &mut app);
+                }
+                ctx.window().unwrap().request_redraw();
             }
             Event::RedrawRequested(_) => {
-                app.render(&ctx);
+                app.render(&ctx, ctx.time().alpha());
             }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } => input_source.event(
+                &mut ctx,
+                InputEvent::MouseMotion(vec2(dx as f32, dy as f32)),
+            ),
+            Event::LoopDestroyed => input_source.finish(),
+            Event::Suspended => app.on_suspend(&ctx),
+            Event::Resumed => app.on_resume(&ctx),
             Event::WindowEvent {
                 window_id: _,
                 event,
-            } => match event {
-                WindowEvent::CloseRequested => control_flow.set_exit(),
-                WindowEvent::KeyboardInput { input, .. } => {
-                    if let Some(keycode) = input.virtual_keycode {
-                        if input.state == ElementState::Pressed {
-                            ctx.input.press(keycode);
-                        } else {
-                            ctx.input.release(keycode);
+            } => {
+                // Live input must not reach the app while a recording drives it.
+                if !(input_source.is_replaying() && is_input_event(&event)) {
+                    app.on_window_event(&ctx, &event);
+                }
+                match event {
+                    WindowEvent::CloseRequested if app.on_exit(&ctx) => control_flow.set_exit(),
+                    WindowEvent::Focused(focused) => app.on_focus_changed(&ctx, focused),
+                    WindowEvent::Resized(size)
+                    | WindowEvent::ScaleFactorChanged {
+                        new_inner_size: &mut size,
+                        ..
+                    } if size.width > 0 && size.height > 0 => {
+                        ctx.resize(size.width, size.height);
+                        app.resize(&ctx, size.width, size.height);
+                    }
+                    WindowEvent::CursorMoved { position, .. } => input_source.event(
+                        &mut ctx,
+                        InputEvent::CursorMoved(Some(vec2(position.x as f32, position.y as f32))),
+                    ),
+                    WindowEvent::ReceivedCharacter(c) => {
+                        input_source.event(&mut ctx, InputEvent::Character(c))
+                    }
+                    WindowEvent::Ime(ime) => input_source.event(
+                        &mut ctx,
+                        match ime {
+                            Ime::Enabled => InputEvent::ImeEnabled,
+                            Ime::Preedit(text, cursor) => InputEvent::ImePreedit(text, cursor),
+                            Ime::Commit(text) => InputEvent::ImeCommit(text),
+                            Ime::Disabled => InputEvent::ImeDisabled,
+                        },
+                    ),
+                    WindowEvent::ModifiersChanged(modifiers) => {
+                        input_source.event(&mut ctx, InputEvent::ModifiersChanged(modifiers))
+                    }
+                    WindowEvent::CursorLeft { .. } => {
+                        input_source.event(&mut ctx, InputEvent::CursorMoved(None))
+                    }
+                    WindowEvent::MouseInput { state, button, .. } => input_source.event(
+                        &mut ctx,
+                        match state {
+                            ElementState::Pressed => InputEvent::MousePressed(button),
+                            ElementState::Released => InputEvent::MouseReleased(button),
+                        },
+                    ),
+                    WindowEvent::MouseWheel { delta, .. } => input_source.event(
+                        &mut ctx,
+                        InputEvent::Scroll(match delta {
+                            MouseScrollDelta::LineDelta(x, y) => vec2(x, y),
+                            MouseScrollDelta::PixelDelta(pos) => {
+                                vec2(pos.x as f32, pos.y as f32) / PIXELS_PER_LINE
+                            }
+                        }),
+                    ),
+                    WindowEvent::KeyboardInput { input, .. } => {
+                        let physical = PhysicalKey(input.scancode);
+                        input_source.event(
+                            &mut ctx,
+                            if input.state == ElementState::Pressed {
+                                InputEvent::PhysicalKeyPressed(physical)
+                            } else {
+                                InputEvent::PhysicalKeyReleased(physical)
+                            },
+                        );
+                        if let Some(keycode) = input.virtual_keycode {
+                            if input.state == ElementState::Pressed {
+                                if Some(keycode) == screenshot_key && !ctx.input.is_pressed(keycode)
+                                {
+                                    // Named after the frame rendered next, which is saved.
+                                    ctx.request_screenshot(format!(
+                                        "screenshot-{}.png",
+                                        ctx.frame_count() + 1
+                                    ));
+                                }
+                                input_source.event(&mut ctx, InputEvent::KeyPressed(keycode));
+                            } else {
+                                input_source.event(&mut ctx, InputEvent::KeyReleased(keycode));
+                            }
                         }
                     }
+                    _ => {}
                 }
-                _ => {}
-            },
+            }
             _ => {}
         });
     }
 }
 
+pub struct EngineBuilder {
+    title: Option<String>,
+    size: Option<LogicalSize<u32>>,
+    resizable: bool,
+    fullscreen: bool,
+    present_mode: Option<wgpu::PresentMode>,
+    backends: wgpu::Backends,
+    power_preference: wgpu::PowerPreference,
+    features: wgpu::Features,
+    limits: wgpu::Limits,
+    force_fallback_adapter: bool,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self {
+            title: None,
+            size: None,
+            resizable: true,
+            fullscreen: false,
+            present_mode: None,
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+impl EngineBuilder {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Initial inner size of the window in logical pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some(LogicalSize::new(width, height));
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Borderless fullscreen on the current monitor.
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Falls back to `Fifo` if the surface does not support `present_mode`.
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = Some(present_mode);
+        self
+    }
+
+    /// Shorthand for `Fifo` when on and `AutoNoVsync` when off.
+    pub fn vsync(self, vsync: bool) -> Self {
+        self.present_mode(if vsync {
+            wgpu::PresentMode::Fifo
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        })
+    }
+
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn features(mut self, features: wgpu::Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn limits(mut self, limits: wgpu::Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn build(self) -> Engine {
+        self.try_build().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_build(self) -> Result<Engine, Error> {
+        let event_loop = EventLoop::new();
+        let mut window = WindowBuilder::new()
+            .with_visible(false)
+            .with_resizable(self.resizable);
+        if let Some(title) = &self.title {
+            window = window.with_title(title);
+        }
+        if let Some(size) = self.size {
+            window = window.with_inner_size(size);
+        }
+        if self.fullscreen {
+            window = window.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        let window = window.build(&event_loop).map_err(Error::CreateWindow)?;
+
+        let ctx = Context::new(window, &self)?;
+
+        Ok(Engine {
+            event_loop,
+            ctx,
+            screenshot_key: None,
+            input_source: InputSource::Live,
+        })
+    }
+
+    /// Creates a headless [`Context`] with this adapter and device configuration.
+    /// Window and present settings are ignored.
+    pub fn build_headless(self, width: u32, height: u32) -> Result<Context, Error> {
+        smol::block_on(Context::headless_async(width, height, &self))
+    }
+
+    fn instance(&self) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        })
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Context {
     pub fn instance(&self) -> &wgpu::Instance {
         &self.instance
@@ -105,8 +408,13 @@ impl Context {
         &self.queue
     }
 
-    pub fn surface(&self) -> &wgpu::Surface {
-        &self.surface
+    /// Returns `None` for a headless context.
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
+        self.surface.as_ref()
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
     }
 
     pub fn config(&self) -> &wgpu::SurfaceConfiguration {
@@ -117,8 +425,79 @@ impl Context {
         &self.input
     }
 
+    /// For feeding input to a headless context; see also [`InputPlayback`].
+    pub fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// Runs one update of `app` that simulates `delta`, for driving a headless context.
+    pub fn step<A: App + ?Sized>(&mut self, app: &mut A, delta: Duration) {
+        self.time.set_delta(delta);
+        self.update_app(app);
+    }
+
+    pub(crate) fn update_app<A: App + ?Sized>(&mut self, app: &mut A) {
+        app.update(self);
+        self.time.end_update();
+        self.input.next_tick(self.time.delta());
+        self.tick_count += 1;
+    }
+
+    /// Returns `None` for a headless context.
+    pub fn window(&self) -> Option<&Window> {
+        self.window.as_ref()
+    }
+
+    /// Locks the cursor in place, or confines it to the window where locking is not
+    /// supported. Use [`Input::mouse_delta`] to read motion while grabbed.
+    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
+        let Some(window) = self.window() else {
+            return Ok(());
+        };
+        if grab {
+            window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+        } else {
+            window.set_cursor_grab(CursorGrabMode::None)
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(window) = self.window() {
+            window.set_cursor_visible(visible);
+        }
+    }
+
+    /// Enables IME composition, reported through [`Input::preedit`] and [`Input::text`].
+    /// Disabled by default, so that keys reach the game directly.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        if let Some(window) = self.window() {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    /// Places the IME candidate window at a physical position in the window,
+    /// usually just below the text cursor.
+    pub fn set_ime_position(&self, x: f32, y: f32) {
+        if let Some(window) = self.window() {
+            window.set_ime_position(PhysicalPosition::new(x, y));
+        }
+    }
+
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// Number of updates run so far, which differs from the frame count with a
+    /// fixed timestep.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Number of frames begun with [`Context::begin_frame`].
     pub fn frame_count(&self) -> u64 {
-        self.frame_count
+        self.render_frame.get()
     }
 
     pub fn size(&self) -> (u32, u32) {
@@ -126,6 +505,19 @@ impl Context {
         (config.width, config.height)
     }
 
+    /// Reconfigures the surface, or recreates the offscreen target of a headless context.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width;
+        self.config.height = height;
+        self.input.set_window_size(width, height);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.config),
+            None => {
+                self.offscreen = Some(Arc::new(Self::create_offscreen(&self.device, &self.config)))
+            }
+        }
+    }
+
     pub fn aspect_ratio(&self) -> f32 {
         let (w, h) = self.size();
         w as f32 / h as f32
@@ -139,56 +531,335 @@ impl Context {
         &self.texture_bind_group_layout
     }
 
-    pub fn surface_texture(&self) -> SurfaceTexture {
-        let surface = self.surface.get_current_texture().unwrap();
-        let texture = Texture {
-            view: surface
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default()),
-            bind_group: None,
+    /// Layout of the bind groups of depth textures, an unfilterable `texture_2d<f32>`
+    /// at binding 0 to read with `textureLoad`.
+    pub fn depth_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.depth_bind_group_layout
+    }
+
+    /// Returns the next swapchain image, or the offscreen target of a headless context.
+    ///
+    /// While a screenshot is pending the returned texture is a readable capture target
+    /// that is copied to the swapchain on [`SurfaceTexture::present`].
+    ///
+    /// Returns `None` when the frame should be skipped, see [`Context::try_surface_texture`].
+    pub fn surface_texture(&self) -> Option<SurfaceTexture<'_>> {
+        self.try_surface_texture()
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`Context::surface_texture`], but reports fatal errors instead of panicking.
+    ///
+    /// A lost or outdated surface is reconfigured and acquired again. Returns
+    /// `Ok(None)` if that still fails or the acquisition timed out, in which case the
+    /// frame should be skipped. Only running out of memory is an error.
+    pub fn try_surface_texture(&self) -> Result<Option<SurfaceTexture<'_>>, Error> {
+        let (surface, texture) = match &self.surface {
+            Some(surface) => {
+                let Some(surface) = self.acquire_surface_texture(surface)? else {
+                    return Ok(None);
+                };
+                let texture = if self.screenshot.borrow().is_some() {
+                    let (width, height) = self.size();
+                    self.create_texture_with_format(width, height, self.config.format)
+                } else {
+                    Texture::unbound(&surface.texture, None)
+                };
+                (Some(surface), texture)
+            }
+            None => {
+                let offscreen = self.offscreen.as_ref().unwrap();
+                let texture = Texture::unbound(offscreen, Some(offscreen.clone()));
+                (None, texture)
+            }
+        };
+        Ok(Some(SurfaceTexture {
+            ctx: self,
+            surface,
+            texture,
+            screenshot: self.screenshot.take(),
+        }))
+    }
+
+    fn acquire_surface_texture(
+        &self,
+        surface: &wgpu::Surface,
+    ) -> Result<Option<wgpu::SurfaceTexture>, Error> {
+        let mut reconfigured = false;
+        loop {
+            match surface.get_current_texture() {
+                Ok(texture) => return Ok(Some(texture)),
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) if !reconfigured => {
+                    log::debug!("surface lost or outdated, reconfiguring");
+                    surface.configure(&self.device, &self.config);
+                    reconfigured = true;
+                }
+                Err(err @ wgpu::SurfaceError::OutOfMemory) => {
+                    return Err(Error::SurfaceTexture(err))
+                }
+                Err(err) => {
+                    log::warn!("skipping frame: {err}");
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Saves the frame of the next [`Context::surface_texture`] as a PNG when it is presented.
+    pub fn request_screenshot(&self, path: impl Into<PathBuf>) {
+        *self.screenshot.borrow_mut() = Some(path.into());
+    }
+
+    /// Copies `texture` back to CPU memory, blocking until the GPU is done.
+    pub fn read_texture(&self, texture: &Texture) -> Image {
+        self.try_read_texture(texture)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Fails for swapchain images, for which [`Context::request_screenshot`] is
+    /// the way, for textures without `COPY_SRC` usage, for formats other than
+    /// 8-bit and float RGBA and `Depth32Float`, for depth on adapters that
+    /// cannot copy it, such as GL, and when mapping the copy fails.
+    pub fn try_read_texture(&self, texture: &Texture) -> Result<Image, Error> {
+        let unreadable = |reason| Error::UnreadableTexture {
+            format: texture.format,
+            reason,
+        };
+        let texture = texture
+            .texture
+            .as_deref()
+            .ok_or_else(|| unreadable("swapchain images cannot be copied"))?;
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(unreadable("created without COPY_SRC usage"));
+        }
+        if !can_decode(texture.format()) {
+            return Err(unreadable("unsupported format"));
+        }
+        if texture.format().has_depth_aspect()
+            && !self
+                .adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES)
+        {
+            return Err(unreadable("depth copies are not supported by the adapter"));
+        }
+        self.read_raw_texture(texture)
+    }
+
+    fn read_raw_texture(&self, texture: &wgpu::Texture) -> Result<Image, Error> {
+        let size = texture.size();
+        let format = texture.format();
+        let aspect = if format.has_depth_aspect() {
+            wgpu::TextureAspect::DepthOnly
+        } else {
+            wgpu::TextureAspect::All
         };
-        SurfaceTexture { surface, texture }
+        let block_size = format.block_size(Some(aspect)).unwrap();
+        let unpadded_bytes_per_row = size.width * block_size;
+        let bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: bytes_per_row as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("readback"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect,
+                ..texture.as_image_copy()
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+        self.queue().submit([encoder.finish()]);
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = sender.send(res);
+        });
+        self.device().poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(Error::MapBuffer)?;
+
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks(bytes_per_row as usize)
+            .flat_map(|row| {
+                row[..unpadded_bytes_per_row as usize]
+                    .chunks(block_size as usize)
+                    .map(|texel| decode_texel(format, texel))
+            })
+            .collect();
+        drop(data);
+        buffer.unmap();
+
+        Ok(Image::new(size.width, size.height, pixels))
+    }
+
+    /// Creates a context without a window or surface.
+    ///
+    /// Any adapter is accepted, including the software fallback adapter, and
+    /// [`Context::surface_texture`] hands out an offscreen `Rgba16Float` target
+    /// of the given size.
+    pub fn headless(width: u32, height: u32) -> Self {
+        Self::try_headless(width, height).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_headless(width: u32, height: u32) -> Result<Self, Error> {
+        EngineBuilder::default().build_headless(width, height)
     }
 
-    fn new(window: &Window) -> Self {
-        smol::block_on(Self::new_async(window))
+    fn new(window: Window, builder: &EngineBuilder) -> Result<Self, Error> {
+        smol::block_on(Self::new_async(window, builder))
     }
 
-    async fn new_async(window: &Window) -> Self {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
-        let surface = unsafe { instance.create_surface(&window).unwrap() };
+    async fn new_async(window: Window, builder: &EngineBuilder) -> Result<Self, Error> {
+        let instance = builder.instance();
+        let surface = unsafe { instance.create_surface(&window) }.map_err(Error::CreateSurface)?;
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
+                power_preference: builder.power_preference,
+                force_fallback_adapter: builder.force_fallback_adapter,
                 compatible_surface: Some(&surface),
             })
             .await
-            .unwrap();
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
+            .ok_or_else(|| Self::no_adapter(&instance))?;
+        let (device, queue) = Self::request_device(&adapter, builder).await?;
         let size = window.inner_size();
         let mut config = surface
             .get_default_config(&adapter, size.width, size.height)
-            .unwrap();
-        if surface
-            .get_capabilities(&adapter)
+            .ok_or_else(|| Error::UnsupportedSurface {
+                adapter: adapter.get_info(),
+            })?;
+        let capabilities = surface.get_capabilities(&adapter);
+        if capabilities
             .formats
             .contains(&wgpu::TextureFormat::Rgba16Float)
         {
             config.format = wgpu::TextureFormat::Rgba16Float;
         }
+        if let Some(present_mode) = builder.present_mode {
+            if capabilities.present_modes.contains(&present_mode)
+                || matches!(
+                    present_mode,
+                    wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+                )
+            {
+                config.present_mode = present_mode;
+            } else {
+                log::warn!("present mode {present_mode:?} is not supported, using Fifo");
+                config.present_mode = wgpu::PresentMode::Fifo;
+            }
+        }
         surface.configure(&device, &config);
 
+        let mut ctx = Self::from_parts(instance, adapter, device, queue, Some(surface), config);
+        ctx.window = Some(window);
+        Ok(ctx)
+    }
+
+    async fn headless_async(
+        width: u32,
+        height: u32,
+        builder: &EngineBuilder,
+    ) -> Result<Self, Error> {
+        let instance = builder.instance();
+        let mut options = wgpu::RequestAdapterOptions {
+            power_preference: builder.power_preference,
+            force_fallback_adapter: builder.force_fallback_adapter,
+            compatible_surface: None,
+        };
+        let adapter = match instance.request_adapter(&options).await {
+            Some(adapter) => adapter,
+            None => {
+                options.force_fallback_adapter = true;
+                instance
+                    .request_adapter(&options)
+                    .await
+                    .ok_or_else(|| Self::no_adapter(&instance))?
+            }
+        };
+        let (device, queue) = Self::request_device(&adapter, builder).await?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba16Float,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Ok(Self::from_parts(
+            instance, adapter, device, queue, None, config,
+        ))
+    }
+
+    fn no_adapter(instance: &wgpu::Instance) -> Error {
+        Error::NoAdapter {
+            available: instance
+                .enumerate_adapters(wgpu::Backends::all())
+                .map(|adapter| adapter.get_info())
+                .collect(),
+        }
+    }
+
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+        builder: &EngineBuilder,
+    ) -> Result<(wgpu::Device, wgpu::Queue), Error> {
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: builder.features,
+                    limits: builder.limits.clone(),
+                },
+                None,
+            )
+            .await
+            .map_err(|source| Error::RequestDevice {
+                adapter: adapter.get_info(),
+                source,
+            })
+    }
+
+    fn from_parts(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: Option<wgpu::Surface>,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let offscreen = surface
+            .is_none()
+            .then(|| Arc::new(Self::create_offscreen(&device, &config)));
+
+        let mut input = Input::default();
+        input.set_window_size(config.width, config.height);
+
         let camera_bind_group_layout = Camera::bind_group_layout(&device);
 
         let texture_bind_group_layout =
@@ -205,6 +876,22 @@ impl Context {
                     count: None,
                 }],
             });
+        let depth_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("depth"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        // Bound as a float texture rather than `texture_depth_2d`, which
+                        // cannot be loaded from on the GL backend.
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
 
         Self {
             instance,
@@ -213,16 +900,144 @@ impl Context {
             queue,
             surface,
             config,
+            offscreen,
 
             camera_bind_group_layout,
             texture_bind_group_layout,
+            depth_bind_group_layout,
+
+            input,
+            time: Time::default(),
+            tick_count: 0,
+            screenshot: RefCell::new(None),
+            screenshot_blit: OnceCell::new(),
+            clear_pipelines: RefCell::default(),
+            render_targets: RenderTargetPool::default(),
+            render_frame: Cell::new(0),
 
-            input: Input::default(),
-            frame_count: 0,
+            window: None,
         }
     }
 
+    /// The pipeline of [`ClearColor::render_viewport`] for `format`, created on
+    /// first use.
+    fn clear_pipeline(&self, format: wgpu::TextureFormat) -> Rc<wgpu::RenderPipeline> {
+        let mut pipelines = self.clear_pipelines.borrow_mut();
+        let pipeline = pipelines.entry(format).or_insert_with(|| {
+            let shader = self
+                .device
+                .create_shader_module(wgpu::include_wgsl!("shader/clear.wgsl"));
+            let layout = self
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("clear"),
+                    bind_group_layouts: &[],
+                    push_constant_ranges: &[],
+                });
+            let replace = wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Constant,
+                dst_factor: wgpu::BlendFactor::Zero,
+                operation: wgpu::BlendOperation::Add,
+            };
+            Rc::new(
+                self.device
+                    .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("clear"),
+                        layout: Some(&layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        primitive: wgpu::PrimitiveState {
+                            topology: wgpu::PrimitiveTopology::TriangleStrip,
+                            ..Default::default()
+                        },
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_main",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format,
+                                blend: Some(wgpu::BlendState {
+                                    color: replace,
+                                    alpha: replace,
+                                }),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        multiview: None,
+                    }),
+            )
+        });
+        pipeline.clone()
+    }
+
+    fn create_offscreen(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    pub fn begin_frame(&self) -> Frame<'_> {
+        let frame = self.render_frame.get() + 1;
+        self.render_frame.set(frame);
+        self.render_targets.trim(frame);
+
+        let encoder = self
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frame"),
+            });
+        Frame { ctx: self, encoder }
+    }
+
     pub fn create_texture(&self, width: u32, height: u32) -> Texture {
+        self.create_texture_with_format(width, height, wgpu::TextureFormat::Rgba16Float)
+    }
+
+    pub fn create_texture_with_format(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Texture {
+        self.create_texture_with_usage(
+            width,
+            height,
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        )
+    }
+
+    /// The texture gets a bind group only if `usage` contains `TEXTURE_BINDING`, with
+    /// [`Context::depth_bind_group_layout`] for depth formats.
+    pub fn create_texture_with_usage(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Texture {
         let tex = self.device().create_texture(&wgpu::TextureDescriptor {
             label: None,
             size: wgpu::Extent3d {
@@ -233,32 +1048,71 @@ impl Context {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba16Float, // self.config().format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            format,
+            usage,
             view_formats: &[],
         });
 
         let view = tex.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let bind_group = self.device().create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &self.texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&view),
-            }],
-        });
+        let bind_group = usage
+            .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+            .then(|| {
+                self.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: if format.has_depth_aspect() {
+                        &self.depth_bind_group_layout
+                    } else {
+                        &self.texture_bind_group_layout
+                    },
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    }],
+                })
+            });
 
         Texture {
+            width,
+            height,
+            format,
+            texture: Some(Arc::new(tex)),
             view,
-            bind_group: Some(bind_group),
+            bind_group,
         }
     }
+
+    pub fn render_targets(&self) -> &RenderTargetPool {
+        &self.render_targets
+    }
+
+    /// Hands out a temporary target from the pool, reusing a free one with the same
+    /// description when possible.
+    pub fn acquire_render_target(&self, desc: RenderTargetDesc) -> PooledTexture {
+        self.render_targets
+            .acquire(self, desc, self.render_frame.get())
+    }
 }
 
 pub trait App {
     fn update(&mut self, ctx: &Context);
-    fn render(&mut self, ctx: &Context);
+    /// `alpha` is [`Time::alpha`](crate::time::Time::alpha), for interpolating
+    /// between the previous and current fixed update.
+    fn render(&mut self, ctx: &Context, alpha: f32);
+    /// Called after the surface has been reconfigured to the new window size.
+    fn resize(&mut self, _ctx: &Context, _width: u32, _height: u32) {}
+    /// Called once the window is visible, right before the event loop starts.
+    fn init(&mut self, _ctx: &Context) {}
+    /// Called for every window event before the engine handles it, except for
+    /// keyboard, mouse and IME events while replaying an input recording.
+    fn on_window_event(&mut self, _ctx: &Context, _event: &WindowEvent) {}
+    fn on_focus_changed(&mut self, _ctx: &Context, _focused: bool) {}
+    fn on_suspend(&mut self, _ctx: &Context) {}
+    fn on_resume(&mut self, _ctx: &Context) {}
+    /// Called when the window is asked to close. Returning `false` keeps it open.
+    fn on_exit(&mut self, _ctx: &Context) -> bool {
+        true
+    }
 }
 
 pub struct ClearColor {
@@ -266,46 +1120,212 @@ pub struct ClearColor {
 }
 
 impl ClearColor {
-    pub fn render(&mut self, ctx: &Context, dst: &Texture) {
-        let mut encoder = ctx
-            .device()
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("clear"),
-            });
+    pub fn render(&mut self, frame: &mut Frame, dst: &Texture) {
         {
-            let _pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("clear"),
+            let _pass = frame
+                .encoder()
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("clear"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &dst.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(self.color),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+        }
+    }
+
+    /// Clears only `viewport` of `dst`, such as the background of a
+    /// picture-in-picture camera, leaving the rest of the target as it is.
+    ///
+    /// `dst` must have a blendable format.
+    pub fn render_viewport(&mut self, frame: &mut Frame, dst: &Texture, viewport: Viewport) {
+        let [x, y, width, height] = viewport.scissor(dst.width(), dst.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        let pipeline = frame.ctx().clear_pipeline(dst.format);
+        let mut pass = frame
+            .encoder()
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("clear viewport"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &dst.view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.color),
+                        load: wgpu::LoadOp::Load,
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
-        }
-        ctx.queue().submit([encoder.finish()]);
+        pass.set_scissor_rect(x, y, width, height);
+        pass.set_pipeline(&pipeline);
+        pass.set_blend_constant(self.color);
+        pass.draw(0..4, 0..1);
+    }
+}
+
+/// Records the commands of one frame into a single encoder that is submitted once.
+#[must_use = "the recorded commands only run once the frame is submitted or presented"]
+pub struct Frame<'a> {
+    ctx: &'a Context,
+    encoder: wgpu::CommandEncoder,
+}
+
+impl<'a> Frame<'a> {
+    pub fn ctx(&self) -> &'a Context {
+        self.ctx
+    }
+
+    pub fn encoder(&mut self) -> &mut wgpu::CommandEncoder {
+        &mut self.encoder
+    }
+
+    pub fn submit(self) {
+        self.ctx.queue().submit([self.encoder.finish()]);
+    }
+
+    pub fn present(self, surface: SurfaceTexture) {
+        self.submit();
+        surface.present();
     }
 }
 
 pub struct Texture {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    pub(crate) texture: Option<Arc<wgpu::Texture>>,
     pub(crate) view: wgpu::TextureView,
     pub(crate) bind_group: Option<wgpu::BindGroup>,
 }
 
-pub struct SurfaceTexture {
-    surface: wgpu::SurfaceTexture,
+impl Texture {
+    fn unbound(texture: &wgpu::Texture, owned: Option<Arc<wgpu::Texture>>) -> Self {
+        let size = texture.size();
+        Self {
+            width: size.width,
+            height: size.height,
+            format: texture.format(),
+            texture: owned,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            bind_group: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+}
+
+pub struct SurfaceTexture<'a> {
+    ctx: &'a Context,
+    surface: Option<wgpu::SurfaceTexture>,
     texture: Texture,
+    screenshot: Option<PathBuf>,
 }
 
-impl SurfaceTexture {
+impl SurfaceTexture<'_> {
     pub fn texture(&self) -> &Texture {
         &self.texture
     }
 
+    /// Does nothing for the offscreen target of a headless context.
+    ///
+    /// Commands rendering to this texture must have been submitted first, which
+    /// [`Frame::present`] takes care of.
     pub fn present(self) {
-        self.surface.present()
+        if let Some(path) = &self.screenshot {
+            match self
+                .ctx
+                .try_read_texture(&self.texture)
+                .and_then(|image| image.save_png(path, Tonemap::Clamp).map_err(Error::Io))
+            {
+                Ok(()) => log::info!("saved screenshot to {}", path.display()),
+                Err(err) => log::error!("failed to save screenshot to {}: {err}", path.display()),
+            }
+        }
+        if let Some(surface) = self.surface {
+            if self.screenshot.is_some() {
+                let dst = Texture::unbound(&surface.texture, None);
+                let mut frame = self.ctx.begin_frame();
+                self.ctx
+                    .screenshot_blit
+                    .get_or_init(|| MagFilter::with_format(self.ctx, self.ctx.config.format))
+                    .render(&mut frame, &self.texture, &dst);
+                frame.submit();
+            }
+            surface.present()
+        }
+    }
+}
+
+fn can_decode(format: wgpu::TextureFormat) -> bool {
+    use wgpu::TextureFormat as F;
+    matches!(
+        format,
+        F::Rgba16Float
+            | F::Rgba32Float
+            | F::Rgba8Unorm
+            | F::Rgba8UnormSrgb
+            | F::Bgra8Unorm
+            | F::Bgra8UnormSrgb
+            | F::Depth32Float
+    )
+}
+
+/// Decodes one texel of a format accepted by [`can_decode`]. Depth is repeated
+/// in the color channels.
+fn decode_texel(format: wgpu::TextureFormat, texel: &[u8]) -> [f32; 4] {
+    use wgpu::TextureFormat as F;
+    match format {
+        F::Rgba16Float => {
+            let mut c = [0.0; 4];
+            for (c, b) in c.iter_mut().zip(texel.chunks(2)) {
+                *c = half::f16::from_le_bytes([b[0], b[1]]).to_f32();
+            }
+            c
+        }
+        F::Rgba32Float => {
+            let mut c = [0.0; 4];
+            for (c, b) in c.iter_mut().zip(texel.chunks(4)) {
+                *c = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            }
+            c
+        }
+        F::Rgba8Unorm | F::Rgba8UnormSrgb | F::Bgra8Unorm | F::Bgra8UnormSrgb => {
+            let mut c = [0, 1, 2, 3].map(|i| texel[i] as f32 / 255.0);
+            if matches!(format, F::Bgra8Unorm | F::Bgra8UnormSrgb) {
+                c.swap(0, 2);
+            }
+            if format.is_srgb() {
+                for c in &mut c[..3] {
+                    *c = image::srgb_to_linear(*c);
+                }
+            }
+            c
+        }
+        F::Depth32Float => {
+            let d = f32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+            [d, d, d, 1.0]
+        }
+        _ => unreachable!("cannot read back texture format {format:?}"),
     }
 }
@@ -0,0 +1,91 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    CreateWindow(winit::error::OsError),
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// No adapter matched the request. `available` lists every adapter the
+    /// instance could enumerate.
+    NoAdapter {
+        available: Vec<wgpu::AdapterInfo>,
+    },
+    RequestDevice {
+        adapter: wgpu::AdapterInfo,
+        source: wgpu::RequestDeviceError,
+    },
+    UnsupportedSurface {
+        adapter: wgpu::AdapterInfo,
+    },
+    SurfaceTexture(wgpu::SurfaceError),
+    /// [`Context::read_texture`](crate::core::Context::read_texture) cannot copy
+    /// or decode the texture.
+    UnreadableTexture {
+        format: wgpu::TextureFormat,
+        reason: &'static str,
+    },
+    /// Mapping the buffer that a texture was copied to for reading back failed.
+    MapBuffer(wgpu::BufferAsyncError),
+    Io(std::io::Error),
+    ParseBindings(ron::error::SpannedError),
+    ParseRecording(ron::error::SpannedError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateWindow(err) => write!(f, "failed to create window: {err}"),
+            Self::CreateSurface(err) => write!(f, "failed to create surface: {err}"),
+            Self::NoAdapter { available } => {
+                write!(f, "no suitable adapter found")?;
+                if available.is_empty() {
+                    write!(f, " (no adapters available)")
+                } else {
+                    write!(f, "; available adapters:")?;
+                    for info in available {
+                        write!(
+                            f,
+                            "\n  {} ({:?}, {:?})",
+                            info.name, info.backend, info.device_type
+                        )?;
+                    }
+                    Ok(())
+                }
+            }
+            Self::RequestDevice { adapter, source } => {
+                write!(
+                    f,
+                    "failed to request device from {}: {source}",
+                    adapter.name
+                )
+            }
+            Self::UnsupportedSurface { adapter } => {
+                write!(f, "surface is not supported by {}", adapter.name)
+            }
+            Self::SurfaceTexture(err) => write!(f, "failed to acquire surface texture: {err}"),
+            Self::UnreadableTexture { format, reason } => {
+                write!(f, "cannot read back {format:?} texture: {reason}")
+            }
+            Self::MapBuffer(err) => write!(f, "failed to map readback buffer: {err}"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::ParseBindings(err) => write!(f, "failed to parse bindings: {err}"),
+            Self::ParseRecording(err) => write!(f, "failed to parse input recording: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CreateWindow(err) => Some(err),
+            Self::CreateSurface(err) => Some(err),
+            Self::RequestDevice { source, .. } => Some(source),
+            Self::SurfaceTexture(err) => Some(err),
+            Self::MapBuffer(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::ParseBindings(err) | Self::ParseRecording(err) => Some(err),
+            Self::NoAdapter { .. }
+            | Self::UnsupportedSurface { .. }
+            | Self::UnreadableTexture { .. } => None,
+        }
+    }
+}
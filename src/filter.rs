@@ -1,4 +1,10 @@
-use crate::core::{Context, Texture};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::{Camera, CAMERA_WGSL},
+    core::{Context, Frame, Texture},
+    pool::RenderTargetDesc,
+};
 
 pub struct MagFilter {
     inner: Filter,
@@ -17,19 +23,75 @@ impl Filter {
         fragment_entry_point: &str,
         input_count: usize,
     ) -> Self {
+        Self::with_format(
+            ctx,
+            shader,
+            vertex_entry_point,
+            fragment_entry_point,
+            input_count,
+            wgpu::TextureFormat::Rgba16Float,
+        )
+    }
+
+    fn with_format(
+        ctx: &Context,
+        shader: &wgpu::ShaderModule,
+        vertex_entry_point: &str,
+        fragment_entry_point: &str,
+        input_count: usize,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let layouts: Vec<_> = (0..input_count)
+            .map(|_| ctx.texture_bind_group_layout())
+            .collect();
+        Self::with_layouts(
+            ctx,
+            shader,
+            vertex_entry_point,
+            fragment_entry_point,
+            &layouts,
+            None,
+            format,
+        )
+    }
+
+    /// A filter whose bind groups follow `layouts`, with the sampler at binding 0 of
+    /// the last group and `params`, if any, as a uniform at binding 1.
+    fn with_layouts(
+        ctx: &Context,
+        shader: &wgpu::ShaderModule,
+        vertex_entry_point: &str,
+        fragment_entry_point: &str,
+        layouts: &[&wgpu::BindGroupLayout],
+        params: Option<&wgpu::Buffer>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let sampler_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let params_entry = wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout_entries = [sampler_entry, params_entry];
         let sampler_bind_group_layout =
             ctx.device()
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     label: Some("filter"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    }],
+                    entries: &layout_entries[..1 + params.is_some() as usize],
                 });
-        let bind_group_layouts: Vec<_> = (0..input_count)
-            .map(|_| ctx.texture_bind_group_layout())
+        let bind_group_layouts: Vec<_> = layouts
+            .iter()
+            .copied()
             .chain(Some(&sampler_bind_group_layout))
             .collect();
         let pipeline_layout =
@@ -59,7 +121,7 @@ impl Filter {
                     module: shader,
                     entry_point: fragment_entry_point,
                     targets: &[Some(wgpu::ColorTargetState {
-                        format: wgpu::TextureFormat::Rgba16Float,
+                        format,
                         blend: None,
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
@@ -80,13 +142,20 @@ impl Filter {
             anisotropy_clamp: 1,
             border_color: None,
         });
+        let entries: Vec<_> = Some(wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Sampler(&sampler),
+        })
+        .into_iter()
+        .chain(params.map(|buffer| wgpu::BindGroupEntry {
+            binding: 1,
+            resource: buffer.as_entire_binding(),
+        }))
+        .collect();
         let sampler_bind_group = ctx.device().create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("filter"),
             layout: &sampler_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Sampler(&sampler),
-            }],
+            entries: &entries,
         });
         Self {
             sampler_bind_group,
@@ -94,41 +163,40 @@ impl Filter {
         }
     }
 
-    fn render(&self, ctx: &Context, src: &Texture, dst: &Texture) {
-        let mut encoder = ctx
-            .device()
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("filter"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &dst.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, src.bind_group.as_ref().unwrap(), &[]);
-            pass.set_bind_group(1, &self.sampler_bind_group, &[]);
-            pass.draw(0..4, 0..1);
-        }
+    fn render(&self, frame: &mut Frame, src: &Texture, dst: &Texture) {
+        self.draw(frame, &[src.bind_group.as_ref().unwrap()], dst, None)
+    }
 
-        ctx.queue().submit([encoder.finish()]);
+    fn render2(&self, frame: &mut Frame, src1: &Texture, src2: &Texture, dst: &Texture) {
+        let bind_groups = [
+            src1.bind_group.as_ref().unwrap(),
+            src2.bind_group.as_ref().unwrap(),
+        ];
+        self.draw(frame, &bind_groups, dst, None)
     }
 
-    fn render2(&self, ctx: &Context, src1: &Texture, src2: &Texture, dst: &Texture) {
-        let mut encoder = ctx
-            .device()
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    /// Renders `src` with `camera` in group 0, the source in group 1 and the depth
+    /// of the camera in group 2, limited to the viewport of the camera.
+    fn render_depth(&self, frame: &mut Frame, camera: &Camera, src: &Texture, dst: &Texture) {
+        let bind_groups = [
+            &camera.bind_group,
+            src.bind_group.as_ref().unwrap(),
+            camera.depth_texture().bind_group.as_ref().unwrap(),
+        ];
+        let scissor = camera.viewport().scissor(dst.width(), dst.height());
+        self.draw(frame, &bind_groups, dst, Some(scissor))
+    }
 
-        {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        bind_groups: &[&wgpu::BindGroup],
+        dst: &Texture,
+        scissor: Option<[u32; 4]>,
+    ) {
+        let mut pass = frame
+            .encoder()
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("filter"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &dst.view,
@@ -141,29 +209,37 @@ impl Filter {
                 depth_stencil_attachment: None,
             });
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, src1.bind_group.as_ref().unwrap(), &[]);
-            pass.set_bind_group(1, src2.bind_group.as_ref().unwrap(), &[]);
-            pass.set_bind_group(2, &self.sampler_bind_group, &[]);
-            pass.draw(0..4, 0..1);
+        if let Some([x, y, width, height]) = scissor {
+            if width == 0 || height == 0 {
+                return;
+            }
+            pass.set_scissor_rect(x, y, width, height);
         }
-
-        ctx.queue().submit([encoder.finish()]);
+        pass.set_pipeline(&self.pipeline);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(i as u32, bind_group, &[]);
+        }
+        pass.set_bind_group(bind_groups.len() as u32, &self.sampler_bind_group, &[]);
+        pass.draw(0..4, 0..1);
     }
 }
 
 impl MagFilter {
     pub fn new(ctx: &Context) -> Self {
+        Self::with_format(ctx, wgpu::TextureFormat::Rgba16Float)
+    }
+
+    pub fn with_format(ctx: &Context, format: wgpu::TextureFormat) -> Self {
         let shader = ctx
             .device()
             .create_shader_module(wgpu::include_wgsl!("./shader/sample.wgsl"));
         Self {
-            inner: Filter::new(ctx, &shader, "vs_main", "fs_main", 1),
+            inner: Filter::with_format(ctx, &shader, "vs_main", "fs_main", 1, format),
         }
     }
 
-    pub fn render(&self, ctx: &Context, src: &Texture, dst: &Texture) {
-        self.inner.render(ctx, src, dst)
+    pub fn render(&self, frame: &mut Frame, src: &Texture, dst: &Texture) {
+        self.inner.render(frame, src, dst)
     }
 }
 
@@ -181,8 +257,8 @@ impl RoundColor {
         }
     }
 
-    pub fn render(&self, ctx: &Context, src: &Texture, dst: &Texture) {
-        self.inner.render(ctx, src, dst)
+    pub fn render(&self, frame: &mut Frame, src: &Texture, dst: &Texture) {
+        self.inner.render(frame, src, dst)
     }
 }
 
@@ -202,9 +278,13 @@ impl GaussianBlur {
         }
     }
 
-    pub fn render(&self, ctx: &Context, src: &Texture, dst: &Texture, tmp: &Texture) {
-        self.horizontal.render(ctx, src, tmp);
-        self.vertical.render(ctx, tmp, dst);
+    pub fn render(&self, frame: &mut Frame, src: &Texture, dst: &Texture) {
+        let (width, height) = dst.size();
+        let tmp = frame
+            .ctx()
+            .acquire_render_target(RenderTargetDesc::new(width, height));
+        self.horizontal.render(frame, src, &tmp);
+        self.vertical.render(frame, &tmp, dst);
     }
 }
 
@@ -227,17 +307,116 @@ impl Bloom {
         }
     }
 
-    pub fn render(
-        &self,
-        ctx: &Context,
-        blur: &GaussianBlur,
-        src: &Texture,
-        dst: &Texture,
-        tmp1: &Texture,
-        tmp2: &Texture,
-    ) {
-        self.threshold.render(ctx, src, tmp1);
-        blur.render(ctx, tmp1, tmp2, dst);
-        self.blend.render2(ctx, src, tmp2, dst);
+    pub fn render(&self, frame: &mut Frame, blur: &GaussianBlur, src: &Texture, dst: &Texture) {
+        let (width, height) = dst.size();
+        let desc = RenderTargetDesc::new(width, height);
+        let bright = frame.ctx().acquire_render_target(desc);
+        let blurred = frame.ctx().acquire_render_target(desc);
+        self.threshold.render(frame, src, &bright);
+        blur.render(frame, &bright, &blurred);
+        self.blend.render2(frame, src, &blurred, dst);
+    }
+}
+
+/// Declarations for the fragment shader of a [`DepthFilter`], following
+/// [`CAMERA_WGSL`]: the source `tex` at group 1, the `depth` of the camera as
+/// `texture_2d<f32>` at group 2, `samp` at group 3, the fullscreen `vs_main` with
+/// its `VsOut`, and `view_position` to turn a pixel back into view space.
+pub const DEPTH_FILTER_WGSL: &str = include_str!("shader/depth_filter.wgsl");
+
+/// A post effect with a custom fragment shader that reads the depth buffer of a
+/// camera after it drew, such as fog or edge detection.
+pub struct DepthFilter {
+    inner: Filter,
+}
+
+impl DepthFilter {
+    /// `fragment` is WGSL defining `fs_main(in: VsOut) -> @location(0) vec4<f32>`,
+    /// appended to [`CAMERA_WGSL`] and [`DEPTH_FILTER_WGSL`].
+    pub fn new(ctx: &Context, fragment: &str) -> Self {
+        Self::create(ctx, fragment, None)
+    }
+
+    /// Like [`DepthFilter::new`], with `params` bound as a uniform at group 3,
+    /// binding 1 for `fragment` to declare.
+    pub fn with_params(ctx: &Context, fragment: &str, params: &wgpu::Buffer) -> Self {
+        Self::create(ctx, fragment, Some(params))
+    }
+
+    fn create(ctx: &Context, fragment: &str, params: Option<&wgpu::Buffer>) -> Self {
+        let shader = ctx
+            .device()
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("depth filter"),
+                source: wgpu::ShaderSource::Wgsl(
+                    format!("{CAMERA_WGSL}{DEPTH_FILTER_WGSL}{fragment}").into(),
+                ),
+            });
+        let layouts = [
+            ctx.camera_bind_group_layout(),
+            ctx.texture_bind_group_layout(),
+            ctx.depth_bind_group_layout(),
+        ];
+        Self {
+            inner: Filter::with_layouts(
+                ctx,
+                &shader,
+                "vs_main",
+                "fs_main",
+                &layouts,
+                params,
+                wgpu::TextureFormat::Rgba16Float,
+            ),
+        }
+    }
+
+    /// Filters the viewport of `camera` in `src` into `dst`, both the size of the
+    /// target the camera drew to.
+    pub fn render(&self, frame: &mut Frame, camera: &Camera, src: &Texture, dst: &Texture) {
+        self.inner.render_depth(frame, camera, src, dst)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FogParams {
+    color: [f32; 3],
+    density: f32,
+}
+
+/// Exponential distance fog, read from the depth buffer of a camera after it drew.
+pub struct Fog {
+    inner: DepthFilter,
+    buffer: wgpu::Buffer,
+}
+
+impl Fog {
+    /// `density` is the fraction of the light lost per unit of distance.
+    pub fn new(ctx: &Context, color: [f32; 3], density: f32) -> Self {
+        let buffer = ctx
+            .device()
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("fog"),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                contents: bytemuck::bytes_of(&FogParams { color, density }),
+            });
+        Self {
+            inner: DepthFilter::with_params(ctx, include_str!("./shader/fog.wgsl"), &buffer),
+            buffer,
+        }
+    }
+
+    pub fn set(&self, ctx: &Context, color: [f32; 3], density: f32) {
+        ctx.queue().write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&FogParams { color, density }),
+        );
+    }
+
+    /// Fogs the viewport of `camera` in `src` into `dst`, both the size of the
+    /// target the camera drew to.
+    pub fn render(&self, frame: &mut Frame, camera: &Camera, src: &Texture, dst: &Texture) {
+        self.inner.render(frame, camera, src, dst)
     }
 }
@@ -0,0 +1,161 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use half::f16;
+
+/// Pixels read back from a texture, as linear RGBA rows from top to bottom.
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemap {
+    #[default]
+    Clamp,
+    Reinhard,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<[f32; 4]>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[[f32; 4]] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [f32; 4] {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({x}, {y}) is outside of the {}x{} image",
+            self.width,
+            self.height
+        );
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Loads an 8-bit PNG as saved by [`Image::save_png`], such as a golden image
+    /// to compare a render against.
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+        let channels = info.color_type.samples();
+        let pixels = data[..info.buffer_size()]
+            .chunks(channels)
+            .map(|texel| {
+                let c = |i: usize| texel[i] as f32 / 255.0;
+                let (rgb, alpha) = match channels {
+                    1 => ([c(0); 3], 1.0),
+                    2 => ([c(0); 3], c(1)),
+                    3 => ([c(0), c(1), c(2)], 1.0),
+                    _ => ([c(0), c(1), c(2)], c(3)),
+                };
+                let [r, g, b] = rgb.map(srgb_to_linear);
+                [r, g, b, alpha]
+            })
+            .collect();
+        Ok(Self::new(info.width, info.height, pixels))
+    }
+
+    /// Saves as an 8-bit sRGB PNG.
+    pub fn save_png(&self, path: impl AsRef<Path>, tonemap: Tonemap) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+
+        let data: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|&[r, g, b, a]| {
+                let [r, g, b] = [r, g, b].map(|c| {
+                    let c = match tonemap {
+                        Tonemap::Clamp => c,
+                        Tonemap::Reinhard => c / (1.0 + c),
+                    };
+                    to_u8(linear_to_srgb(c))
+                });
+                [r, g, b, to_u8(a)]
+            })
+            .collect();
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Saves the pixels unchanged as tightly packed little-endian RGBA half floats
+    /// with no header.
+    pub fn save_raw_f16(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for pixel in &self.pixels {
+            for c in pixel {
+                file.write_all(&f16::from_f32(*c).to_le_bytes())?;
+            }
+        }
+        file.flush()
+    }
+}
+
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Image {
+        Image::new(2, 2, vec![[0.0; 4], [0.25; 4], [0.5; 4], [1.0; 4]])
+    }
+
+    #[test]
+    fn get_reads_rows_from_the_top() {
+        assert_eq!(image().get(1, 0), [0.25; 4]);
+        assert_eq!(image().get(0, 1), [0.5; 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the 2x2 image")]
+    fn get_rejects_x_past_the_row() {
+        image().get(2, 0);
+    }
+}
@@ -1,32 +1,811 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, hash::Hash, time::Duration};
 
-use winit::event::VirtualKeyCode;
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, MouseButton, ScanCode, VirtualKeyCode};
+
+/// A change to the [`Input`] state, as produced by the window or a recording.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    KeyPressed(VirtualKeyCode),
+    KeyReleased(VirtualKeyCode),
+    PhysicalKeyPressed(PhysicalKey),
+    PhysicalKeyReleased(PhysicalKey),
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    /// Physical position, `None` when the cursor left the window.
+    CursorMoved(Option<Vec2>),
+    MouseMotion(Vec2),
+    Scroll(Vec2),
+    ModifiersChanged(ModifiersState),
+    /// A character typed on the keyboard, outside of IME composition.
+    Character(char),
+    ImeEnabled,
+    /// Text being composed and the byte range of its cursor, empty to clear.
+    ImePreedit(String, Option<(usize, usize)>),
+    ImeCommit(String),
+    ImeDisabled,
+}
+
+/// A key identified by its position on the keyboard rather than the character it
+/// types, so that bindings such as WASD stay in place on AZERTY or Dvorak layouts.
+///
+/// Wraps the platform scancode. The constants name keys by their position on a
+/// US QWERTY keyboard.
+///
+/// Keys with a constant are saved by that name, such as `W` or `LShift`, and
+/// loaded as the scancode of the current platform, so binding files can be
+/// shared between platforms. Other keys are saved as `Scancode(..)`, which only
+/// means the same key on the platform that saved it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "KeyName", into = "KeyName")]
+pub struct PhysicalKey(pub ScanCode);
+
+/// The serialized form of a [`PhysicalKey`].
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum KeyName {
+    Escape,
+    Tab,
+    Q,
+    W,
+    E,
+    R,
+    A,
+    S,
+    D,
+    F,
+    Z,
+    X,
+    C,
+    Space,
+    LShift,
+    LControl,
+    Scancode(ScanCode),
+}
+
+impl KeyName {
+    const NAMED: [(Self, PhysicalKey); 16] = [
+        (Self::Escape, PhysicalKey::ESCAPE),
+        (Self::Tab, PhysicalKey::TAB),
+        (Self::Q, PhysicalKey::Q),
+        (Self::W, PhysicalKey::W),
+        (Self::E, PhysicalKey::E),
+        (Self::R, PhysicalKey::R),
+        (Self::A, PhysicalKey::A),
+        (Self::S, PhysicalKey::S),
+        (Self::D, PhysicalKey::D),
+        (Self::F, PhysicalKey::F),
+        (Self::Z, PhysicalKey::Z),
+        (Self::X, PhysicalKey::X),
+        (Self::C, PhysicalKey::C),
+        (Self::Space, PhysicalKey::SPACE),
+        (Self::LShift, PhysicalKey::LSHIFT),
+        (Self::LControl, PhysicalKey::LCONTROL),
+    ];
+}
+
+impl From<PhysicalKey> for KeyName {
+    fn from(key: PhysicalKey) -> Self {
+        Self::NAMED
+            .iter()
+            .find(|&&(_, named)| named == key)
+            .map_or(Self::Scancode(key.0), |&(name, _)| name)
+    }
+}
+
+impl From<KeyName> for PhysicalKey {
+    fn from(name: KeyName) -> Self {
+        match name {
+            KeyName::Scancode(scancode) => Self(scancode),
+            name => KeyName::NAMED
+                .iter()
+                .find(|&&(named, _)| named == name)
+                .map(|&(_, key)| key)
+                .unwrap(),
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+impl PhysicalKey {
+    pub const ESCAPE: Self = Self(0x01);
+    pub const TAB: Self = Self(0x0f);
+    pub const Q: Self = Self(0x10);
+    pub const W: Self = Self(0x11);
+    pub const E: Self = Self(0x12);
+    pub const R: Self = Self(0x13);
+    pub const A: Self = Self(0x1e);
+    pub const S: Self = Self(0x1f);
+    pub const D: Self = Self(0x20);
+    pub const F: Self = Self(0x21);
+    pub const Z: Self = Self(0x2c);
+    pub const X: Self = Self(0x2d);
+    pub const C: Self = Self(0x2e);
+    pub const SPACE: Self = Self(0x39);
+    pub const LSHIFT: Self = Self(0x2a);
+    pub const LCONTROL: Self = Self(0x1d);
+}
+
+#[cfg(target_os = "macos")]
+impl PhysicalKey {
+    pub const ESCAPE: Self = Self(0x35);
+    pub const TAB: Self = Self(0x30);
+    pub const Q: Self = Self(0x0c);
+    pub const W: Self = Self(0x0d);
+    pub const E: Self = Self(0x0e);
+    pub const R: Self = Self(0x0f);
+    pub const A: Self = Self(0x00);
+    pub const S: Self = Self(0x01);
+    pub const D: Self = Self(0x02);
+    pub const F: Self = Self(0x03);
+    pub const Z: Self = Self(0x06);
+    pub const X: Self = Self(0x07);
+    pub const C: Self = Self(0x08);
+    pub const SPACE: Self = Self(0x31);
+    pub const LSHIFT: Self = Self(0x38);
+    pub const LCONTROL: Self = Self(0x3b);
+}
+
+/// Text being composed in an IME.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// Byte range of the cursor or selection within `text`, `None` to hide it.
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// Auto-repeat timing for [`Input::is_repeated_with`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// Time a key has to be held before it starts repeating.
+    pub delay: Duration,
+    pub interval: Duration,
+}
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(500),
+            interval: Duration::from_millis(50),
+        }
+    }
+}
+
+impl KeyRepeat {
+    /// Number of repeats, including the initial press, after holding for `held`.
+    fn count(&self, held: Duration) -> u128 {
+        match held.checked_sub(self.delay) {
+            Some(d) => 2 + d.as_nanos() / self.interval.as_nanos().max(1),
+            None => 1,
+        }
+    }
+}
+
+/// A key pressed while exactly the given modifiers are held, such as Ctrl+S.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: ModifiersState,
+    pub key: VirtualKeyCode,
+}
+
+impl Chord {
+    pub fn new(modifiers: ModifiersState, key: VirtualKeyCode) -> Self {
+        Self { modifiers, key }
+    }
+}
 
 #[derive(Default, Debug)]
 pub struct Input {
-    keyboard: HashMap<VirtualKeyCode, u32>,
+    keyboard: HashMap<VirtualKeyCode, ButtonState>,
+    physical_keys: HashMap<PhysicalKey, ButtonState>,
+    mouse_buttons: HashMap<MouseButton, ButtonState>,
+    modifiers: ModifiersState,
+    cursor_position: Option<Vec2>,
+    window_size: Vec2,
+    mouse_delta: Vec2,
+    scroll_delta: Vec2,
+    text: String,
+    preedit: Option<Preedit>,
+    ime_enabled: bool,
+    layers: RefCell<Vec<String>>,
+    consumed: RefCell<Consumed>,
+    /// The physical key each virtual key was last pressed with, kept while
+    /// either is held so that consuming one also consumes the other.
+    key_links: HashMap<VirtualKeyCode, PhysicalKey>,
+    pending_physical: Option<PhysicalKey>,
+}
+
+/// Highest layer depth that consumed each input during this tick.
+#[derive(Default, Debug)]
+struct Consumed {
+    keys: HashMap<VirtualKeyCode, usize>,
+    physical_keys: HashMap<PhysicalKey, usize>,
+    mouse_buttons: HashMap<MouseButton, usize>,
+    all: usize,
+}
+
+/// `ticks` counts the ticks since the press and is `!0` in the tick of the release.
+///
+/// `pressed_this_tick` keeps a press that was released again within the same
+/// tick, such as a touchpad tap, visible as just pressed.
+#[derive(Clone, Copy, Debug)]
+struct ButtonState {
+    ticks: u32,
+    held: Duration,
+    prev_held: Duration,
+    pressed_this_tick: bool,
+}
+
+impl ButtonState {
+    const PRESSED: Self = Self {
+        ticks: 0,
+        held: Duration::ZERO,
+        prev_held: Duration::ZERO,
+        pressed_this_tick: true,
+    };
+    const RELEASED: Self = Self {
+        ticks: !0,
+        held: Duration::ZERO,
+        prev_held: Duration::ZERO,
+        pressed_this_tick: false,
+    };
+
+    fn is_pressed(&self) -> bool {
+        self.ticks != !0
+    }
+    fn is_just_pressed(&self) -> bool {
+        self.pressed_this_tick
+    }
+    fn is_just_released(&self) -> bool {
+        self.ticks == !0
+    }
+}
+
+fn press<K: Eq + Hash>(map: &mut HashMap<K, ButtonState>, key: K) {
+    // Key repeats from the OS must not restart the press.
+    if !map.get(&key).is_some_and(ButtonState::is_pressed) {
+        map.insert(key, ButtonState::PRESSED);
+    }
+}
+
+fn release<K: Eq + Hash>(map: &mut HashMap<K, ButtonState>, key: K) {
+    let pressed_this_tick = map.get(&key).is_some_and(|s| s.pressed_this_tick);
+    map.insert(
+        key,
+        ButtonState {
+            pressed_this_tick,
+            ..ButtonState::RELEASED
+        },
+    );
+}
+
+fn consume<K: Eq + Hash>(consumed: &mut HashMap<K, usize>, key: K, depth: usize) {
+    let entry = consumed.entry(key).or_default();
+    *entry = (*entry).max(depth);
+}
+
+/// The state of `key` as seen from `depth`, hidden if a higher layer consumed it.
+fn visible<K: Eq + Hash>(
+    map: &HashMap<K, ButtonState>,
+    consumed: &HashMap<K, usize>,
+    all: usize,
+    key: K,
+    depth: usize,
+) -> Option<ButtonState> {
+    if all.max(consumed.get(&key).copied().unwrap_or(0)) > depth {
+        None
+    } else {
+        map.get(&key).copied()
+    }
+}
+
+fn next_tick<K>(map: &mut HashMap<K, ButtonState>, delta: Duration) {
+    map.retain(|_, state| {
+        state.ticks = state.ticks.wrapping_add(1);
+        state.prev_held = state.held;
+        state.held += delta;
+        state.pressed_this_tick = false;
+        state.ticks != 0
+    });
 }
 
 impl Input {
+    pub fn apply(&mut self, event: &InputEvent) {
+        // The physical key event of a key press comes right before its virtual one.
+        let pending_physical = self.pending_physical.take();
+        match *event {
+            InputEvent::KeyPressed(keycode) => {
+                if let Some(key) = pending_physical {
+                    self.key_links.insert(keycode, key);
+                }
+                self.press(keycode);
+            }
+            InputEvent::KeyReleased(keycode) => self.release(keycode),
+            InputEvent::PhysicalKeyPressed(key) => {
+                self.pending_physical = Some(key);
+                press(&mut self.physical_keys, key);
+            }
+            InputEvent::PhysicalKeyReleased(key) => release(&mut self.physical_keys, key),
+            InputEvent::MousePressed(button) => self.press_mouse(button),
+            InputEvent::MouseReleased(button) => self.release_mouse(button),
+            InputEvent::CursorMoved(position) => self.move_cursor(position),
+            InputEvent::MouseMotion(delta) => self.move_mouse(delta),
+            InputEvent::Scroll(delta) => self.scroll(delta),
+            InputEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            InputEvent::Character(c) => self.text.push(c),
+            InputEvent::ImeEnabled => self.ime_enabled = true,
+            InputEvent::ImePreedit(ref text, cursor) => {
+                self.preedit = (!text.is_empty()).then(|| Preedit {
+                    text: text.clone(),
+                    cursor,
+                });
+            }
+            InputEvent::ImeCommit(ref text) => {
+                self.preedit = None;
+                self.text.push_str(text);
+            }
+            InputEvent::ImeDisabled => {
+                self.ime_enabled = false;
+                self.preedit = None;
+            }
+        }
+    }
     pub(crate) fn press(&mut self, keycode: VirtualKeyCode) {
-        self.keyboard.insert(keycode, 0);
+        press(&mut self.keyboard, keycode);
     }
     pub(crate) fn release(&mut self, keycode: VirtualKeyCode) {
-        self.keyboard.insert(keycode, !0);
+        release(&mut self.keyboard, keycode);
+    }
+    pub(crate) fn press_mouse(&mut self, button: MouseButton) {
+        press(&mut self.mouse_buttons, button);
     }
-    pub(crate) fn next_tick(&mut self) {
-        self.keyboard.retain(|_, time| {
-            *time = (*time).wrapping_add(1);
-            *time != 0
+    pub(crate) fn release_mouse(&mut self, button: MouseButton) {
+        release(&mut self.mouse_buttons, button);
+    }
+    pub(crate) fn move_cursor(&mut self, position: Option<Vec2>) {
+        self.cursor_position = position;
+    }
+    pub(crate) fn move_mouse(&mut self, delta: Vec2) {
+        self.mouse_delta += delta;
+    }
+    pub(crate) fn scroll(&mut self, delta: Vec2) {
+        self.scroll_delta += delta;
+    }
+    pub(crate) fn set_window_size(&mut self, width: u32, height: u32) {
+        self.window_size = vec2(width as f32, height as f32);
+    }
+    /// Advances by one tick, in which pressed keys were held for `delta`.
+    pub(crate) fn next_tick(&mut self, delta: Duration) {
+        next_tick(&mut self.keyboard, delta);
+        next_tick(&mut self.physical_keys, delta);
+        next_tick(&mut self.mouse_buttons, delta);
+        let (keyboard, physical_keys) = (&self.keyboard, &self.physical_keys);
+        self.key_links.retain(|keycode, key| {
+            keyboard.contains_key(keycode) || physical_keys.contains_key(key)
         });
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll_delta = Vec2::ZERO;
+        self.text.clear();
+        *self.consumed.get_mut() = Consumed::default();
+    }
+    fn key_state(&self, keycode: VirtualKeyCode, depth: usize) -> Option<ButtonState> {
+        let consumed = self.consumed.borrow();
+        visible(&self.keyboard, &consumed.keys, consumed.all, keycode, depth)
+    }
+    fn physical_key_state(&self, key: PhysicalKey, depth: usize) -> Option<ButtonState> {
+        let consumed = self.consumed.borrow();
+        visible(
+            &self.physical_keys,
+            &consumed.physical_keys,
+            consumed.all,
+            key,
+            depth,
+        )
+    }
+    fn mouse_state(&self, button: MouseButton, depth: usize) -> Option<ButtonState> {
+        let consumed = self.consumed.borrow();
+        visible(
+            &self.mouse_buttons,
+            &consumed.mouse_buttons,
+            consumed.all,
+            button,
+            depth,
+        )
+    }
+    fn is_all_consumed(&self, depth: usize) -> bool {
+        self.consumed.borrow().all > depth
+    }
+    /// Pushes a layer above the others, for example for a console or a pause menu.
+    /// Its view is returned by [`Input::layer`].
+    pub fn push_layer(&self, name: impl Into<String>) {
+        self.layers.borrow_mut().push(name.into());
+    }
+    /// Removes the topmost layer and returns its name.
+    pub fn pop_layer(&self) -> Option<String> {
+        self.layers.borrow_mut().pop()
+    }
+    /// Removes the topmost layer called `name`, wherever it is in the stack.
+    pub fn remove_layer(&self, name: &str) -> bool {
+        let mut layers = self.layers.borrow_mut();
+        match layers.iter().rposition(|layer| layer == name) {
+            Some(i) => {
+                layers.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn has_layer(&self, name: &str) -> bool {
+        self.layers.borrow().iter().any(|layer| layer == name)
+    }
+    /// The view of the topmost layer called `name`, or `None` if it is not pushed.
+    pub fn layer(&self, name: &str) -> Option<InputLayer<'_>> {
+        let depth = self
+            .layers
+            .borrow()
+            .iter()
+            .rposition(|layer| layer == name)?;
+        Some(InputLayer {
+            input: self,
+            depth: depth + 1,
+        })
+    }
+    /// The view below every pushed layer. The queries on [`Input`] itself read
+    /// from this layer.
+    pub fn base(&self) -> InputLayer<'_> {
+        InputLayer {
+            input: self,
+            depth: 0,
+        }
     }
     pub fn is_pressed(&self, keycode: VirtualKeyCode) -> bool {
-        self.keyboard.get(&keycode).is_some_and(|&t| t != !0)
+        self.base().is_pressed(keycode)
     }
     pub fn is_just_pressed(&self, keycode: VirtualKeyCode) -> bool {
-        self.keyboard.get(&keycode).is_some_and(|&t| t == 0)
+        self.base().is_just_pressed(keycode)
     }
     pub fn is_just_released(&self, keycode: VirtualKeyCode) -> bool {
-        self.keyboard.get(&keycode).is_some_and(|&t| t == !0)
+        self.base().is_just_released(keycode)
+    }
+    /// Ticks since `keycode` was pressed, `0` in the tick of the press.
+    pub fn held_ticks(&self, keycode: VirtualKeyCode) -> Option<u32> {
+        self.key_state(keycode, 0)
+            .filter(ButtonState::is_pressed)
+            .map(|s| s.ticks)
+    }
+    /// Simulated time since `keycode` was pressed.
+    pub fn held_time(&self, keycode: VirtualKeyCode) -> Option<Duration> {
+        self.key_state(keycode, 0)
+            .filter(ButtonState::is_pressed)
+            .map(|s| s.held)
+    }
+    pub fn held_for(&self, keycode: VirtualKeyCode, duration: Duration) -> bool {
+        self.held_time(keycode).is_some_and(|held| held >= duration)
+    }
+    /// True in the tick of the press and then periodically while held, with the
+    /// default [`KeyRepeat`] timing.
+    pub fn is_repeated(&self, keycode: VirtualKeyCode) -> bool {
+        self.is_repeated_with(keycode, KeyRepeat::default())
+    }
+    pub fn is_repeated_with(&self, keycode: VirtualKeyCode, repeat: KeyRepeat) -> bool {
+        self.base().is_repeated_with(keycode, repeat)
+    }
+    pub fn is_pressed_physical(&self, key: PhysicalKey) -> bool {
+        self.base().is_pressed_physical(key)
+    }
+    pub fn is_just_pressed_physical(&self, key: PhysicalKey) -> bool {
+        self.base().is_just_pressed_physical(key)
+    }
+    pub fn is_just_released_physical(&self, key: PhysicalKey) -> bool {
+        self.base().is_just_released_physical(key)
+    }
+    pub fn held_time_physical(&self, key: PhysicalKey) -> Option<Duration> {
+        self.physical_key_state(key, 0)
+            .filter(ButtonState::is_pressed)
+            .map(|s| s.held)
+    }
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+    pub fn is_chord_pressed(&self, chord: Chord) -> bool {
+        self.modifiers == chord.modifiers && self.is_pressed(chord.key)
+    }
+    pub fn is_chord_just_pressed(&self, chord: Chord) -> bool {
+        self.modifiers == chord.modifiers && self.is_just_pressed(chord.key)
+    }
+    pub fn is_chord_repeated(&self, chord: Chord) -> bool {
+        self.modifiers == chord.modifiers && self.is_repeated(chord.key)
+    }
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.base().is_mouse_pressed(button)
+    }
+    pub fn is_mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.base().is_mouse_just_pressed(button)
+    }
+    pub fn is_mouse_just_released(&self, button: MouseButton) -> bool {
+        self.base().is_mouse_just_released(button)
+    }
+    /// Cursor position in physical pixels from the top-left corner of the window,
+    /// or `None` while the cursor is outside of it.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+    /// Cursor position scaled to `0.0..=1.0` across the window, top-left origin.
+    pub fn cursor_position_normalized(&self) -> Option<Vec2> {
+        self.cursor_position
+            .map(|pos| pos / self.window_size.max(Vec2::ONE))
+    }
+    /// Raw mouse motion during this tick, unaffected by cursor grab or acceleration.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.base().mouse_delta()
+    }
+    /// Scroll during this tick in lines, positive `y` scrolling up.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.base().scroll_delta()
+    }
+    /// Text typed or committed by the IME during this tick. Includes control
+    /// characters such as backspace (`'\u{8}'`) and return (`'\r'`).
+    pub fn text(&self) -> &str {
+        self.base().text()
+    }
+    /// The IME composition in progress, to be drawn at the text cursor.
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+    pub fn is_ime_enabled(&self) -> bool {
+        self.ime_enabled
+    }
+}
+
+/// The view of [`Input`] from one layer of the stack, which hides what the layers
+/// above it consumed during this tick.
+///
+/// Layers only hide input from those below them, so the higher layers have to
+/// be updated first in each tick.
+#[derive(Clone, Copy, Debug)]
+pub struct InputLayer<'a> {
+    input: &'a Input,
+    depth: usize,
+}
+
+impl<'a> From<&'a Input> for InputLayer<'a> {
+    fn from(input: &'a Input) -> Self {
+        input.base()
+    }
+}
+
+impl<'a> InputLayer<'a> {
+    pub fn input(&self) -> &'a Input {
+        self.input
+    }
+    /// Hides `keycode` from the layers below until the next tick, together with
+    /// the physical key it was pressed with.
+    pub fn consume(&self, keycode: VirtualKeyCode) {
+        let mut consumed = self.input.consumed.borrow_mut();
+        consume(&mut consumed.keys, keycode, self.depth);
+        if let Some(&key) = self.input.key_links.get(&keycode) {
+            consume(&mut consumed.physical_keys, key, self.depth);
+        }
+    }
+    /// Hides `key` from the layers below until the next tick, together with the
+    /// virtual keys it produced.
+    pub fn consume_physical(&self, key: PhysicalKey) {
+        let mut consumed = self.input.consumed.borrow_mut();
+        consume(&mut consumed.physical_keys, key, self.depth);
+        for (&keycode, _) in self.input.key_links.iter().filter(|(_, &k)| k == key) {
+            consume(&mut consumed.keys, keycode, self.depth);
+        }
+    }
+    pub fn consume_mouse(&self, button: MouseButton) {
+        consume(
+            &mut self.input.consumed.borrow_mut().mouse_buttons,
+            button,
+            self.depth,
+        );
+    }
+    /// Hides every key, button, mouse motion, scroll and text from the layers
+    /// below until the next tick, as a modal menu would.
+    pub fn consume_all(&self) {
+        let mut consumed = self.input.consumed.borrow_mut();
+        consumed.all = consumed.all.max(self.depth);
+    }
+    pub fn is_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        self.input
+            .key_state(keycode, self.depth)
+            .is_some_and(|s| s.is_pressed())
+    }
+    pub fn is_just_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        self.input
+            .key_state(keycode, self.depth)
+            .is_some_and(|s| s.is_just_pressed())
+    }
+    pub fn is_just_released(&self, keycode: VirtualKeyCode) -> bool {
+        self.input
+            .key_state(keycode, self.depth)
+            .is_some_and(|s| s.is_just_released())
+    }
+    pub fn is_repeated(&self, keycode: VirtualKeyCode) -> bool {
+        self.is_repeated_with(keycode, KeyRepeat::default())
+    }
+    pub fn is_repeated_with(&self, keycode: VirtualKeyCode, repeat: KeyRepeat) -> bool {
+        self.input.key_state(keycode, self.depth).is_some_and(|s| {
+            s.is_just_pressed()
+                || (s.is_pressed() && repeat.count(s.held) > repeat.count(s.prev_held))
+        })
+    }
+    pub fn is_pressed_physical(&self, key: PhysicalKey) -> bool {
+        self.input
+            .physical_key_state(key, self.depth)
+            .is_some_and(|s| s.is_pressed())
+    }
+    pub fn is_just_pressed_physical(&self, key: PhysicalKey) -> bool {
+        self.input
+            .physical_key_state(key, self.depth)
+            .is_some_and(|s| s.is_just_pressed())
+    }
+    pub fn is_just_released_physical(&self, key: PhysicalKey) -> bool {
+        self.input
+            .physical_key_state(key, self.depth)
+            .is_some_and(|s| s.is_just_released())
+    }
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.input
+            .mouse_state(button, self.depth)
+            .is_some_and(|s| s.is_pressed())
+    }
+    pub fn is_mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.input
+            .mouse_state(button, self.depth)
+            .is_some_and(|s| s.is_just_pressed())
+    }
+    pub fn is_mouse_just_released(&self, button: MouseButton) -> bool {
+        self.input
+            .mouse_state(button, self.depth)
+            .is_some_and(|s| s.is_just_released())
+    }
+    pub fn mouse_delta(&self) -> Vec2 {
+        if self.input.is_all_consumed(self.depth) {
+            Vec2::ZERO
+        } else {
+            self.input.mouse_delta
+        }
+    }
+    pub fn scroll_delta(&self) -> Vec2 {
+        if self.input.is_all_consumed(self.depth) {
+            Vec2::ZERO
+        } else {
+            self.input.scroll_delta
+        }
+    }
+    pub fn text(&self) -> &'a str {
+        if self.input.is_all_consumed(self.depth) {
+            ""
+        } else {
+            &self.input.text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn press_and_release_in_one_tick_is_seen() {
+        let mut input = Input::default();
+        input.apply(&InputEvent::MousePressed(MouseButton::Left));
+        input.apply(&InputEvent::MouseReleased(MouseButton::Left));
+        input.apply(&InputEvent::KeyPressed(VirtualKeyCode::Space));
+        input.apply(&InputEvent::KeyReleased(VirtualKeyCode::Space));
+        assert!(input.is_mouse_just_pressed(MouseButton::Left));
+        assert!(input.is_mouse_just_released(MouseButton::Left));
+        assert!(!input.is_mouse_pressed(MouseButton::Left));
+        assert!(input.is_just_pressed(VirtualKeyCode::Space));
+        assert!(input.is_just_released(VirtualKeyCode::Space));
+        assert!(input.is_repeated(VirtualKeyCode::Space));
+
+        input.next_tick(TICK);
+        assert!(!input.is_mouse_just_pressed(MouseButton::Left));
+        assert!(!input.is_mouse_just_released(MouseButton::Left));
+        assert!(!input.is_just_pressed(VirtualKeyCode::Space));
+    }
+
+    const REPEAT: KeyRepeat = KeyRepeat {
+        delay: Duration::from_millis(500),
+        interval: Duration::from_millis(50),
+    };
+
+    /// Ticks at which Space repeats while held for `ticks` ticks of `delta`.
+    fn repeats(delta: Duration, ticks: u32) -> Vec<u32> {
+        let mut input = Input::default();
+        input.press(VirtualKeyCode::Space);
+        let mut repeats = vec![];
+        for tick in 0..ticks {
+            if input.is_repeated_with(VirtualKeyCode::Space, REPEAT) {
+                repeats.push(tick);
+            }
+            input.next_tick(delta);
+        }
+        repeats
+    }
+
+    #[test]
+    fn repeat_count_starts_exactly_at_delay() {
+        assert_eq!(REPEAT.count(Duration::ZERO), 1);
+        assert_eq!(REPEAT.count(Duration::from_millis(499)), 1);
+        assert_eq!(REPEAT.count(Duration::from_millis(500)), 2);
+        assert_eq!(REPEAT.count(Duration::from_millis(549)), 2);
+        assert_eq!(REPEAT.count(Duration::from_millis(550)), 3);
+    }
+
+    #[test]
+    fn key_repeats_after_delay_then_every_interval() {
+        assert_eq!(repeats(Duration::from_millis(50), 14), [0, 10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn key_repeats_once_per_tick_when_interval_is_shorter() {
+        // Several repeats within one 100 ms tick still report a single repeat.
+        assert_eq!(repeats(Duration::from_millis(100), 8), [0, 5, 6, 7]);
+    }
+
+    #[test]
+    fn release_resets_repeat_and_held_time() {
+        let mut input = Input::default();
+        input.press(VirtualKeyCode::Space);
+        for _ in 0..6 {
+            input.next_tick(Duration::from_millis(100));
+        }
+        assert!(input.held_for(VirtualKeyCode::Space, Duration::from_millis(600)));
+        assert!(!input.held_for(VirtualKeyCode::Space, Duration::from_millis(601)));
+        input.release(VirtualKeyCode::Space);
+        assert!(!input.is_repeated_with(VirtualKeyCode::Space, REPEAT));
+        assert_eq!(input.held_time(VirtualKeyCode::Space), None);
+        input.next_tick(Duration::from_millis(100));
+
+        input.press(VirtualKeyCode::Space);
+        assert_eq!(input.held_time(VirtualKeyCode::Space), Some(Duration::ZERO));
+        input.next_tick(Duration::from_millis(100));
+        assert!(!input.is_repeated_with(VirtualKeyCode::Space, REPEAT));
+        assert!(!input.held_for(VirtualKeyCode::Space, Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn chord_needs_exactly_its_modifiers() {
+        let save = Chord::new(ModifiersState::CTRL, VirtualKeyCode::S);
+        let mut input = Input::default();
+        input.press(VirtualKeyCode::S);
+        assert!(!input.is_chord_just_pressed(save));
+
+        input.apply(&InputEvent::ModifiersChanged(ModifiersState::CTRL));
+        assert!(input.is_chord_pressed(save));
+        assert!(input.is_chord_just_pressed(save));
+        assert!(input.is_chord_repeated(save));
+
+        input.apply(&InputEvent::ModifiersChanged(
+            ModifiersState::CTRL | ModifiersState::SHIFT,
+        ));
+        assert!(!input.is_chord_pressed(save));
+        assert!(!input.is_chord_just_pressed(save));
+
+        input.apply(&InputEvent::ModifiersChanged(ModifiersState::CTRL));
+        input.next_tick(Duration::from_millis(10));
+        assert!(input.is_chord_pressed(save));
+        assert!(!input.is_chord_just_pressed(save));
+    }
+
+    #[test]
+    fn press_is_just_pressed_for_one_tick() {
+        let mut input = Input::default();
+        input.press(VirtualKeyCode::Space);
+        assert!(input.is_just_pressed(VirtualKeyCode::Space));
+        input.next_tick(TICK);
+        assert!(!input.is_just_pressed(VirtualKeyCode::Space));
+        assert!(input.is_pressed(VirtualKeyCode::Space));
+        input.release(VirtualKeyCode::Space);
+        assert!(!input.is_just_pressed(VirtualKeyCode::Space));
+        assert!(input.is_just_released(VirtualKeyCode::Space));
     }
 }
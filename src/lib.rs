@@ -1,5 +1,14 @@
+pub mod action;
+pub mod bounds;
 pub mod camera;
 pub mod core;
+pub mod error;
 pub mod filter;
+pub mod image;
 pub mod input;
+pub mod pool;
+pub mod record;
 pub mod renderer;
+pub mod time;
+
+pub use error::Error;
@@ -1,20 +1,33 @@
 use cg8::{
-    camera::{Camera, Projection},
+    action::{ActionMap, Binding},
+    camera::{Camera, DepthMode, FlyController, OrbitController, Projection},
     core::{App, ClearColor, Context, Engine, Texture},
-    filter::{Bloom, GaussianBlur, MagFilter, RoundColor},
+    filter::{Bloom, Fog, GaussianBlur, MagFilter, RoundColor},
+    input::PhysicalKey,
+    record::InputRecording,
     renderer::{
         ColoredPolygonRenderer, ColoredPolygons, ColoredVertex, ColoredVertices, Indices, Instance,
         Instances,
     },
 };
-use glam::{vec3, vec3a, Affine3A, Mat4};
+use glam::{vec3, Affine3A, Mat4};
 use winit::event::VirtualKeyCode;
 
 fn main() {
     env_logger::init();
 
-    let engine = Engine::new();
-    let app = MyApp::new(&engine.context());
+    let mut engine = Engine::builder().title("cg8").build();
+    engine.set_screenshot_key(Some(VirtualKeyCode::F12));
+    let mut args = std::env::args().skip(1);
+    match (args.next().as_deref(), args.next()) {
+        (Some("--record"), Some(path)) => engine.record_input(path),
+        (Some("--replay"), Some(path)) => match InputRecording::load(&path) {
+            Ok(recording) => engine.replay_input(recording),
+            Err(err) => log::error!("failed to load {path}: {err}"),
+        },
+        _ => {}
+    }
+    let app = MyApp::new(engine.context());
     engine.run(app);
 }
 
@@ -28,20 +41,27 @@ pub struct MyApp {
     bloom: Bloom,
     mag_filter: MagFilter,
     round_color: RoundColor,
+    fog: Fog,
     frames: Vec<Texture>,
+    actions: ActionMap,
+    fly: FlyController,
+    orbit: OrbitController,
+    orbiting: bool,
 }
 
+const SCALE: u32 = 4;
+
 impl MyApp {
     pub fn new(ctx: &Context) -> Self {
         dbg!(ctx.config().format);
         let transform = Affine3A::IDENTITY;
         let projection =
             Projection::perspective(std::f32::consts::FRAC_PI_4, ctx.aspect_ratio(), 0.1, 1000.0);
-        // Projection::Orthographic { left: 0, right: 800, bottom: 500, top: 0, near: (), far: () }
-        let scale = 4;
-        let width = ctx.size().0 / scale;
-        let height = ctx.size().1 / scale;
-        let camera = Camera::new(ctx, transform, projection, width, height);
+        let width = ctx.size().0 / SCALE;
+        let height = ctx.size().1 / SCALE;
+        let mut camera = Camera::new(ctx, transform, projection, width, height);
+        camera.depth_mode = DepthMode::ReverseZInfinite;
+        camera.update_buffer(ctx);
         let renderer = ColoredPolygonRenderer::new(ctx);
         let polygons = octahedron(ctx);
         let t = Mat4::from_translation(vec3(0.0, 0.0, 10.0));
@@ -55,7 +75,18 @@ impl MyApp {
         let bloom = Bloom::new(ctx);
         let mag_filter = MagFilter::new(ctx);
         let round_color = RoundColor::new(ctx);
-        let frames: Vec<Texture> = (0..4).map(|_| ctx.create_texture(width, height)).collect();
+        let fog = Fog::new(ctx, [0.1, 0.1, 0.15], 0.05);
+        let frames: Vec<Texture> = (0..2).map(|_| ctx.create_texture(width, height)).collect();
+        let actions = match ActionMap::load("bindings.ron") {
+            Ok(actions) => actions,
+            Err(err) => {
+                log::info!("using default bindings: {err}");
+                let mut actions = ActionMap::new();
+                FlyController::bind_defaults(&mut actions);
+                actions.bind("switch_camera", Binding::Physical(PhysicalKey::TAB));
+                actions
+            }
+        };
         Self {
             camera,
             clear_color: ClearColor {
@@ -73,74 +104,79 @@ impl MyApp {
             bloom,
             mag_filter,
             round_color,
+            fog,
             frames,
+            actions,
+            fly: FlyController::new(),
+            orbit: OrbitController::new(vec3(0.0, 0.0, 5.0), 5.0),
+            orbiting: false,
         }
     }
 }
 
 impl App for MyApp {
     fn update(&mut self, ctx: &Context) {
-        let mut dx = 0;
-        let mut dz = 0;
-        if ctx.input().is_pressed(VirtualKeyCode::A) {
-            dx -= 1;
-        }
-        if ctx.input().is_pressed(VirtualKeyCode::D) {
-            dx += 1;
+        if self
+            .actions
+            .action_just_pressed(ctx.input(), "switch_camera")
+        {
+            self.orbiting = !self.orbiting;
         }
-        if ctx.input().is_pressed(VirtualKeyCode::W) {
-            dz += 1;
-        }
-        if ctx.input().is_pressed(VirtualKeyCode::S) {
-            dz -= 1;
-        }
-        if dx != 0 || dz != 0 {
-            let r = 4.0 / 60.0;
-            let d = vec3a(dx as f32, 0.0, dz as f32).normalize() * r;
-            self.camera.transform.translation += d;
-            self.camera.update_buffer(ctx);
+        if self.orbiting {
+            self.orbit.update(ctx, ctx.input(), &mut self.camera);
+        } else {
+            self.fly
+                .update(ctx, ctx.input(), &self.actions, &mut self.camera);
         }
 
         let t = Mat4::from_translation(vec3(0.0, 0.0, 5.0))
             * Mat4::from_axis_angle(
                 vec3(1.0, 2.0, 0.0).normalize(),
-                ctx.frame_count() as f32 / 60.0 / std::f32::consts::PI,
+                ctx.time().elapsed_secs() / std::f32::consts::PI,
             );
 
         self.instances[0].mat = t.to_cols_array_2d();
         self.instances.update_buffer(ctx);
     }
-    fn render(&mut self, ctx: &Context) {
-        self.clear_color.render(ctx, &self.frames[0]);
+    fn render(&mut self, ctx: &Context, _alpha: f32) {
+        let mut frame = ctx.begin_frame();
+        self.clear_color.render(&mut frame, &self.frames[0]);
         self.renderer.render(
-            ctx,
+            &mut frame,
             &self.frames[0],
             &self.polygons,
             &self.instances,
             &self.camera,
         );
+        self.fog
+            .render(&mut frame, &self.camera, &self.frames[0], &self.frames[1]);
         self.round_color
-            .render(ctx, &self.frames[0], &self.frames[1]);
+            .render(&mut frame, &self.frames[1], &self.frames[0]);
         /*
         self.blur
-            .render(ctx, &self.frames[1], &self.frames[0], &self.frames[2]);
+            .render(&mut frame, &self.frames[0], &self.frames[1]);
          */
-        self.bloom.render(
-            ctx,
-            &self.blur,
-            &self.frames[1],
-            &self.frames[0],
-            &self.frames[2],
-            &self.frames[3],
-        );
-        let surface = ctx.surface_texture();
-        self.mag_filter
-            .render(ctx, &self.frames[0], surface.texture());
-        surface.present();
+        self.bloom
+            .render(&mut frame, &self.blur, &self.frames[0], &self.frames[1]);
+        match ctx.surface_texture() {
+            Some(surface) => {
+                self.mag_filter
+                    .render(&mut frame, &self.frames[1], surface.texture());
+                frame.present(surface);
+            }
+            None => frame.submit(),
+        }
+    }
+    fn resize(&mut self, ctx: &Context, width: u32, height: u32) {
+        let width = (width / SCALE).max(1);
+        let height = (height / SCALE).max(1);
+        self.camera.resize(ctx, width, height);
+        self.frames = (0..2).map(|_| ctx.create_texture(width, height)).collect();
     }
 }
 
-fn sphere(ctx: &Context) -> ColoredPolygons {
+#[allow(dead_code, clippy::vec_init_then_push)]
+fn sphere(_ctx: &Context) -> ColoredPolygons {
     fn vertex(x: f32, y: f32, z: f32) -> ColoredVertex {
         let color = [-0.5 * y + 0.5, 0.8, 0.5 * y + 0.5, 1.0];
         ColoredVertex {
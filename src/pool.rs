@@ -0,0 +1,113 @@
+use std::{cell::RefCell, ops::Deref, rc::Rc};
+
+use crate::core::{Context, Texture};
+
+/// Targets that went unused for this many frames are released.
+const MAX_IDLE_FRAMES: u64 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+}
+
+impl RenderTargetDesc {
+    /// An `Rgba16Float` target that can be rendered to, sampled and read back.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        }
+    }
+
+    pub fn format(self, format: wgpu::TextureFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    pub fn usage(self, usage: wgpu::TextureUsages) -> Self {
+        Self { usage, ..self }
+    }
+}
+
+/// Temporary render targets, reused across frames.
+///
+/// A target stays reserved while its [`PooledTexture`] is alive and returns to the
+/// pool when it is dropped.
+#[derive(Default)]
+pub struct RenderTargetPool {
+    targets: RefCell<Vec<PooledTarget>>,
+}
+
+struct PooledTarget {
+    desc: RenderTargetDesc,
+    texture: Rc<Texture>,
+    last_used: u64,
+}
+
+pub struct PooledTexture(Rc<Texture>);
+
+impl Deref for PooledTexture {
+    type Target = Texture;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl RenderTargetPool {
+    /// Number of targets currently held, in use or not.
+    pub fn len(&self) -> usize {
+        self.targets.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.borrow().is_empty()
+    }
+
+    /// Releases every target that is not in use.
+    pub fn clear(&self) {
+        self.targets
+            .borrow_mut()
+            .retain(|target| Rc::strong_count(&target.texture) > 1);
+    }
+
+    pub(crate) fn acquire(
+        &self,
+        ctx: &Context,
+        desc: RenderTargetDesc,
+        frame: u64,
+    ) -> PooledTexture {
+        let mut targets = self.targets.borrow_mut();
+        if let Some(target) = targets
+            .iter_mut()
+            .find(|target| target.desc == desc && Rc::strong_count(&target.texture) == 1)
+        {
+            target.last_used = frame;
+            return PooledTexture(target.texture.clone());
+        }
+
+        let texture = Rc::new(ctx.create_texture_with_usage(
+            desc.width,
+            desc.height,
+            desc.format,
+            desc.usage,
+        ));
+        targets.push(PooledTarget {
+            desc,
+            texture: texture.clone(),
+            last_used: frame,
+        });
+        PooledTexture(texture)
+    }
+
+    pub(crate) fn trim(&self, frame: u64) {
+        self.targets.borrow_mut().retain(|target| {
+            Rc::strong_count(&target.texture) > 1 || frame - target.last_used <= MAX_IDLE_FRAMES
+        });
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fs, mem, path::Path, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{App, Context},
+    error::Error,
+    input::InputEvent,
+};
+
+/// The input events that arrived before one update, and the time it simulated.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputTick {
+    pub delta: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+/// A per-tick input stream that can be saved and replayed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub ticks: Vec<InputTick>,
+}
+
+impl InputRecording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        ron::from_str(&fs::read_to_string(path).map_err(Error::Io)?).map_err(Error::ParseRecording)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let s = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).unwrap();
+        fs::write(path, s).map_err(Error::Io)
+    }
+}
+
+/// Plays an [`InputRecording`] back one tick at a time.
+///
+/// [`Engine::replay_input`](crate::core::Engine::replay_input) uses this in place of
+/// live events; it can also drive a headless [`Context`] directly:
+///
+/// ```no_run
+/// # use cg8::{core::{App, Context}, record::{InputPlayback, InputRecording}};
+/// # fn run(mut app: impl App) -> Result<(), cg8::Error> {
+/// let mut ctx = Context::headless(800, 600);
+/// let mut playback = InputPlayback::new(InputRecording::load("session.ron")?);
+/// while playback.step(&mut ctx, &mut app) {}
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct InputPlayback {
+    recording: InputRecording,
+    next: usize,
+}
+
+impl InputPlayback {
+    pub fn new(recording: InputRecording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.ticks.len()
+    }
+
+    /// Applies the next tick's events and runs one update with its delta.
+    /// Returns `false` once the recording is exhausted.
+    pub fn step<A: App + ?Sized>(&mut self, ctx: &mut Context, app: &mut A) -> bool {
+        let Some(tick) = self.recording.ticks.get(self.next) else {
+            return false;
+        };
+        self.next += 1;
+        for event in &tick.events {
+            ctx.input_mut().apply(event);
+        }
+        ctx.step(app, tick.delta);
+        true
+    }
+}
+
+pub(crate) enum InputSource {
+    Live,
+    Record {
+        path: PathBuf,
+        recording: InputRecording,
+        pending: Vec<InputEvent>,
+    },
+    Replay(InputPlayback),
+}
+
+impl InputSource {
+    pub(crate) fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replay(_))
+    }
+
+    pub(crate) fn record(path: PathBuf) -> Self {
+        Self::Record {
+            path,
+            recording: InputRecording::default(),
+            pending: vec![],
+        }
+    }
+
+    pub(crate) fn event(&mut self, ctx: &mut Context, event: InputEvent) {
+        match self {
+            Self::Live => ctx.input_mut().apply(&event),
+            Self::Record { pending, .. } => {
+                ctx.input_mut().apply(&event);
+                pending.push(event);
+            }
+            Self::Replay(_) => {}
+        }
+    }
+
+    pub(crate) fn update<A: App + ?Sized>(&mut self, ctx: &mut Context, app: &mut A) {
+        match self {
+            Self::Live => ctx.update_app(app),
+            Self::Record {
+                recording, pending, ..
+            } => {
+                recording.ticks.push(InputTick {
+                    delta: ctx.time().delta(),
+                    events: mem::take(pending),
+                });
+                ctx.update_app(app);
+            }
+            Self::Replay(playback) => {
+                if !playback.step(ctx, app) {
+                    log::info!("input replay finished, switching to live input");
+                    *self = Self::Live;
+                    ctx.update_app(app);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        if let Self::Record {
+            path, recording, ..
+        } = self
+        {
+            match recording.save(&*path) {
+                Ok(()) => log::info!("saved input recording to {}", path.display()),
+                Err(err) => log::error!(
+                    "failed to save input recording to {}: {err}",
+                    path.display()
+                ),
+            }
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use std::{
+    cell::RefCell,
     mem,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
 };
 
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
 use wgpu::util::DeviceExt;
 
 use crate::{
-    camera::Camera,
-    core::{Context, Texture},
+    bounds::{Aabb, Ray, Sphere},
+    camera::{Camera, DepthMode},
+    core::{Context, Frame, Texture},
 };
 
 #[repr(C)]
@@ -20,6 +23,8 @@ pub struct ColoredVertex {
 pub struct ColoredVertices {
     data: Vec<ColoredVertex>,
     buffer: wgpu::Buffer,
+    bounds: Aabb,
+    bounding_sphere: Sphere,
 }
 
 impl ColoredVertices {
@@ -31,7 +36,28 @@ impl ColoredVertices {
                 usage: wgpu::BufferUsages::VERTEX,
                 contents: bytemuck::cast_slice(&data),
             });
-        Self { data, buffer }
+        let points = data.iter().map(|v| Vec4::from(v.pos).xyz());
+        let bounds = Aabb::from_points(points.clone()).unwrap_or(Aabb::new(Vec3::ZERO, Vec3::ZERO));
+        let bounding_sphere = Sphere::from_points(points).unwrap_or(Sphere::new(Vec3::ZERO, 0.0));
+        Self {
+            data,
+            buffer,
+            bounds,
+            bounding_sphere,
+        }
+    }
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+    pub fn bounding_sphere(&self) -> Sphere {
+        self.bounding_sphere
+    }
+}
+
+impl Deref for ColoredVertices {
+    type Target = [ColoredVertex];
+    fn deref(&self) -> &Self::Target {
+        &self.data
     }
 }
 
@@ -84,12 +110,12 @@ impl Instances {
 impl Deref for Instances {
     type Target = [Instance];
     fn deref(&self) -> &Self::Target {
-        &*self.data
+        &self.data
     }
 }
 impl DerefMut for Instances {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.data
+        &mut self.data
     }
 }
 
@@ -102,17 +128,120 @@ impl ColoredPolygons {
     pub fn new(vertices: ColoredVertices, indices: Indices) -> Self {
         Self { vertices, indices }
     }
+    pub fn bounds(&self) -> Aabb {
+        self.vertices.bounds()
+    }
+    /// Bounds in model space, used to cull instances outside of the camera.
+    pub fn bounding_sphere(&self) -> Sphere {
+        self.vertices.bounding_sphere()
+    }
+
+    /// The `t` of the closest triangle hit by `ray` with the polygons placed by
+    /// `transform`.
+    pub fn raycast(&self, ray: &Ray, transform: &Mat4) -> Option<f32> {
+        ray.intersect_sphere(&self.bounding_sphere().transform(transform))?;
+        let local = ray.transform(&transform.inverse());
+        let position = |i: u32| Vec4::from(self.vertices[i as usize].pos).xyz();
+        self.indices
+            .data
+            .chunks_exact(3)
+            .filter_map(|tri| {
+                local.intersect_triangle(position(tri[0]), position(tri[1]), position(tri[2]))
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    /// The closest of `instances` hit by `ray`.
+    pub fn pick(&self, instances: &[Instance], ray: &Ray) -> Option<PickHit> {
+        instances
+            .iter()
+            .enumerate()
+            .filter_map(|(instance, data)| {
+                let t = self.raycast(ray, &Mat4::from_cols_array_2d(&data.mat))?;
+                Some(PickHit {
+                    instance,
+                    point: ray.at(t),
+                    t,
+                })
+            })
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit {
+    /// Index into the instances.
+    pub instance: usize,
+    /// World-space position of the hit.
+    pub point: Vec3,
+    /// The [`Ray::at`] parameter of the hit, a distance for normalized rays.
+    pub t: f32,
 }
 
 pub struct ColoredPolygonRenderer {
     pipeline: wgpu::RenderPipeline,
+    reverse_z_pipeline: wgpu::RenderPipeline,
+    culled: RefCell<CulledInstances>,
+}
+
+/// The instances left after culling, for all draws of the current frame.
+///
+/// Each draw appends after the previous ones, as the uploads all happen before
+/// the frame is submitted and must not overwrite each other.
+struct CulledInstances {
+    buffer: wgpu::Buffer,
+    len: wgpu::BufferAddress,
+    frame: u64,
+}
+
+impl CulledInstances {
+    const INITIAL_CAPACITY: wgpu::BufferAddress = 64 * mem::size_of::<Instance>() as u64;
+
+    fn create_buffer(ctx: &Context, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        ctx.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some("visible instances"),
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Uploads `instances` and returns where they are in the buffer.
+    fn push(&mut self, ctx: &Context, instances: &[Instance]) -> Range<wgpu::BufferAddress> {
+        let frame = ctx.frame_count();
+        if self.frame != frame {
+            self.frame = frame;
+            self.len = 0;
+        }
+        let bytes: &[u8] = bytemuck::cast_slice(instances);
+        let size = bytes.len() as wgpu::BufferAddress;
+        if self.len + size > self.buffer.size() {
+            // Earlier draws keep the old buffer alive until the frame is done.
+            let capacity = (self.len + size).next_power_of_two();
+            self.buffer = Self::create_buffer(ctx, capacity);
+            self.len = 0;
+        }
+        ctx.queue().write_buffer(&self.buffer, self.len, bytes);
+        let range = self.len..self.len + size;
+        self.len = range.end;
+        range
+    }
 }
 
 impl ColoredPolygonRenderer {
     pub fn new(ctx: &Context) -> Self {
         let shader = ctx
             .device()
-            .create_shader_module(wgpu::include_wgsl!("shader/colored.wgsl"));
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("colored"),
+                source: wgpu::ShaderSource::Wgsl(
+                    concat!(
+                        include_str!("shader/camera.wgsl"),
+                        include_str!("shader/colored.wgsl")
+                    )
+                    .into(),
+                ),
+            });
         let pipeline_layout =
             ctx.device()
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -120,108 +249,165 @@ impl ColoredPolygonRenderer {
                     bind_group_layouts: &[ctx.camera_bind_group_layout()],
                     push_constant_ranges: &[],
                 });
-        let pipeline = ctx
-            .device()
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("colored"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[
-                        wgpu::VertexBufferLayout {
-                            array_stride: mem::size_of::<ColoredVertex>() as _,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &wgpu::vertex_attr_array![
-                                0 => Float32x4,
-                                1 => Float32x4,
-                            ],
-                        },
-                        wgpu::VertexBufferLayout {
-                            array_stride: mem::size_of::<Instance>() as _,
-                            step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &wgpu::vertex_attr_array![
-                                2 => Float32x4,
-                                3 => Float32x4,
-                                4 => Float32x4,
-                                5 => Float32x4,
-                            ],
-                        },
-                    ],
-                },
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: None,
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::LessEqual,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: wgpu::TextureFormat::Rgba16Float, // self.config().format,
-                        blend: None, // Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-            });
-        Self { pipeline }
+        let create_pipeline = |depth_mode: DepthMode| {
+            ctx.device()
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("colored"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            wgpu::VertexBufferLayout {
+                                array_stride: mem::size_of::<ColoredVertex>() as _,
+                                step_mode: wgpu::VertexStepMode::Vertex,
+                                attributes: &wgpu::vertex_attr_array![
+                                    0 => Float32x4,
+                                    1 => Float32x4,
+                                ],
+                            },
+                            wgpu::VertexBufferLayout {
+                                array_stride: mem::size_of::<Instance>() as _,
+                                step_mode: wgpu::VertexStepMode::Instance,
+                                attributes: &wgpu::vertex_attr_array![
+                                    2 => Float32x4,
+                                    3 => Float32x4,
+                                    4 => Float32x4,
+                                    5 => Float32x4,
+                                ],
+                            },
+                        ],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: None,
+                        unclipped_depth: false,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: wgpu::TextureFormat::Depth32Float,
+                        depth_write_enabled: true,
+                        depth_compare: depth_mode.compare_function(),
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format: wgpu::TextureFormat::Rgba16Float, // self.config().format,
+                            blend: None, // Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    multiview: None,
+                })
+        };
+        Self {
+            pipeline: create_pipeline(DepthMode::Standard),
+            reverse_z_pipeline: create_pipeline(DepthMode::ReverseZInfinite),
+            culled: RefCell::new(CulledInstances {
+                buffer: CulledInstances::create_buffer(ctx, CulledInstances::INITIAL_CAPACITY),
+                len: 0,
+                frame: 0,
+            }),
+        }
+    }
+
+    fn pipeline(&self, depth_mode: DepthMode) -> &wgpu::RenderPipeline {
+        match depth_mode {
+            DepthMode::Standard => &self.pipeline,
+            DepthMode::ReverseZInfinite => &self.reverse_z_pipeline,
+        }
     }
 
     pub fn render(
         &self,
-        ctx: &Context,
+        frame: &mut Frame,
         dst: &Texture,
         data: &ColoredPolygons,
         instances: &Instances,
         camera: &Camera,
     ) {
-        let mut encoder = ctx
-            .device()
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("renderer"),
-            });
+        let (visible, visible_count) = self.cull(frame.ctx(), data, instances, camera);
+        let culled = self.culled.borrow();
+        let instance_buffer = match visible {
+            Some(range) => culled.buffer.slice(range),
+            None => instances.buffer.slice(..),
+        };
+        let depth = camera.depth_buffer();
+        let frame_number = frame.ctx().frame_count();
+        let [x, y, width, height] = camera.viewport_pixels();
+        let [sx, sy, sw, sh] = camera.viewport().scissor(dst.width(), dst.height());
         {
-            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("renderer"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &dst.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &camera.depth_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
+            let mut pass = frame
+                .encoder()
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("renderer"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &dst.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &depth.texture().view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: depth.load_op(frame_number, camera),
+                            store: true,
+                        }),
+                        stencil_ops: None,
                     }),
-                    stencil_ops: None,
-                }),
-            });
+                });
 
-            pass.set_pipeline(&self.pipeline);
+            if visible_count == 0 || sw == 0 || sh == 0 {
+                return;
+            }
+            pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            pass.set_scissor_rect(sx, sy, sw, sh);
+            pass.set_pipeline(self.pipeline(camera.depth_mode));
             pass.set_bind_group(0, &camera.bind_group, &[]);
             pass.set_vertex_buffer(0, data.vertices.buffer.slice(..));
-            pass.set_vertex_buffer(1, instances.buffer.slice(..));
+            pass.set_vertex_buffer(1, instance_buffer);
             pass.set_index_buffer(data.indices.buffer.slice(..), wgpu::IndexFormat::Uint32);
 
-            pass.draw_indexed(0..data.indices.data.len() as u32, 0, 0..1);
+            pass.draw_indexed(0..data.indices.data.len() as u32, 0, 0..visible_count);
+        }
+    }
+
+    /// Uploads the instances that can be seen by `camera` and returns their range
+    /// in the culled buffer, or `None` if all of them can and the original buffer
+    /// can be drawn as is.
+    fn cull(
+        &self,
+        ctx: &Context,
+        data: &ColoredPolygons,
+        instances: &Instances,
+        camera: &Camera,
+    ) -> (Option<Range<wgpu::BufferAddress>>, u32) {
+        let frustum = camera.frustum();
+        let sphere = data.bounding_sphere();
+        let visible: Vec<Instance> = instances
+            .iter()
+            .filter(|instance| {
+                frustum
+                    .intersects_sphere(&sphere.transform(&Mat4::from_cols_array_2d(&instance.mat)))
+            })
+            .copied()
+            .collect();
+        if visible.len() == instances.len() {
+            return (None, visible.len() as u32);
+        }
+        if visible.is_empty() {
+            return (None, 0);
         }
-        ctx.queue().submit([encoder.finish()]);
+        let range = self.culled.borrow_mut().push(ctx, &visible);
+        (Some(range), visible.len() as u32)
     }
 }
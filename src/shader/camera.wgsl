@@ -0,0 +1,19 @@
+// Mirrors `CameraUniform` in camera.rs.
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    time: f32,
+    // x, y, width and height in pixels of the target.
+    viewport: vec4<f32>,
+    near: f32,
+    far: f32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
@@ -0,0 +1,12 @@
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i & 1u);
+    let y = f32(i >> 1u);
+    return vec4(2.0 * x - 1.0, 2.0 * y - 1.0, 0.0, 1.0);
+}
+
+// Blended with the constant color, so that no buffer is needed per clear.
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4(1.0);
+}
@@ -1,7 +1,3 @@
-@group(0)
-@binding(0)
-var<uniform> camera: mat4x4<f32>;
-
 struct VsIn {
     @location(0) pos: vec4<f32>,
     @location(1) color: vec4<f32>,
@@ -20,7 +16,7 @@ struct VsOut {
 fn vs_main(in: VsIn, @builtin(vertex_index) i: u32) -> VsOut {
     var out: VsOut;
     let tr = mat4x4(in.c0, in.c1, in.c2, in.c3);
-    out.pos = camera * tr * in.pos;
+    out.pos = camera.view_proj * tr * in.pos;
     out.color = in.color;
     return out;
 }
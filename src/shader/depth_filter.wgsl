@@ -0,0 +1,29 @@
+@group(1) @binding(0)
+var tex: texture_2d<f32>;
+@group(2) @binding(0)
+var depth: texture_2d<f32>;
+@group(3) @binding(0)
+var samp: sampler;
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VsOut {
+    var out: VsOut;
+    let x = f32(i & 1u);
+    let y = f32(i >> 1u);
+    out.pos = vec4(2.0 * x - 1.0, 2.0 * y - 1.0, 0.0, 1.0);
+    out.tex_coords = vec2(x, 1.0 - y);
+    return out;
+}
+
+// The view-space position drawn at a pixel, in homogeneous coordinates as `w`
+// reaches zero where nothing was drawn under an infinite far plane.
+fn view_position(pixel: vec2<f32>) -> vec4<f32> {
+    let d = textureLoad(depth, vec2<i32>(pixel), 0).r;
+    let uv = (pixel - camera.viewport.xy) / camera.viewport.zw;
+    return camera.inv_proj * vec4(2.0 * uv.x - 1.0, 1.0 - 2.0 * uv.y, d, 1.0);
+}
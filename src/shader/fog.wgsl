@@ -0,0 +1,18 @@
+struct Fog {
+    color: vec3<f32>,
+    density: f32,
+};
+
+@group(3) @binding(1)
+var<uniform> fog: Fog;
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let color = textureSample(tex, samp, in.tex_coords);
+    let view = view_position(in.pos.xy);
+    var visibility = 0.0;
+    if abs(view.w) > 1e-6 {
+        visibility = exp(-fog.density * length(view.xyz / view.w));
+    }
+    return vec4(mix(fog.color, color.rgb, visibility), color.a);
+}
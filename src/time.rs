@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+
+/// Longest frame delta that is simulated; anything longer (a breakpoint, a
+/// dragged window) is treated as this long.
+const MAX_FRAME_DELTA: Duration = Duration::from_millis(250);
+const FPS_SMOOTHING: f32 = 0.9;
+
+#[derive(Debug)]
+pub struct Time {
+    last_frame: Instant,
+    frame_delta: Duration,
+    delta: Duration,
+    elapsed: Duration,
+    fps: f32,
+    fixed_timestep: Option<Duration>,
+    accumulator: Duration,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            last_frame: Instant::now(),
+            frame_delta: Duration::ZERO,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            fps: 0.0,
+            fixed_timestep: None,
+            accumulator: Duration::ZERO,
+        }
+    }
+}
+
+impl Time {
+    /// Time simulated by the current update: the fixed timestep in fixed mode,
+    /// otherwise the frame delta.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Wall-clock time between the last two frames.
+    pub fn frame_delta(&self) -> Duration {
+        self.frame_delta
+    }
+
+    /// Total simulated time, the sum of the deltas of all updates so far.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn fixed_timestep(&self) -> Option<Duration> {
+        self.fixed_timestep
+    }
+
+    /// How far rendering is between the last and the next fixed update, in `0.0..1.0`.
+    ///
+    /// Always `1.0` without a fixed timestep, as the state is then exactly current.
+    pub fn alpha(&self) -> f32 {
+        match self.fixed_timestep {
+            Some(step) => self.accumulator.as_secs_f32() / step.as_secs_f32(),
+            None => 1.0,
+        }
+    }
+
+    pub(crate) fn set_fixed_timestep(&mut self, step: Option<Duration>) {
+        assert!(
+            step.is_none_or(|step| !step.is_zero()),
+            "fixed timestep must not be zero"
+        );
+        self.fixed_timestep = step;
+        self.accumulator = Duration::ZERO;
+    }
+
+    /// Starts a frame and returns how many updates to run in it.
+    pub(crate) fn begin_frame(&mut self) -> u32 {
+        self.begin_frame_at(Instant::now())
+    }
+
+    fn begin_frame_at(&mut self, now: Instant) -> u32 {
+        self.frame_delta = (now - self.last_frame).min(MAX_FRAME_DELTA);
+        self.last_frame = now;
+
+        let fps = 1.0 / self.frame_delta.as_secs_f32().max(f32::EPSILON);
+        self.fps = if self.fps == 0.0 {
+            fps
+        } else {
+            FPS_SMOOTHING * self.fps + (1.0 - FPS_SMOOTHING) * fps
+        };
+
+        match self.fixed_timestep {
+            Some(step) => {
+                self.delta = step;
+                self.accumulator += self.frame_delta;
+                let mut updates = 0;
+                while self.accumulator >= step {
+                    self.accumulator -= step;
+                    updates += 1;
+                }
+                updates
+            }
+            None => {
+                self.delta = self.frame_delta;
+                1
+            }
+        }
+    }
+
+    pub(crate) fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+
+    pub(crate) fn end_update(&mut self) {
+        self.elapsed += self.delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    /// Runs frames `millis` apart and returns the updates of each.
+    fn frames(time: &mut Time, millis: &[u64]) -> Vec<u32> {
+        let mut now = time.last_frame;
+        millis
+            .iter()
+            .map(|&ms| {
+                now += Duration::from_millis(ms);
+                let updates = time.begin_frame_at(now);
+                for _ in 0..updates {
+                    time.end_update();
+                }
+                updates
+            })
+            .collect()
+    }
+
+    fn fixed() -> Time {
+        let mut time = Time::default();
+        time.set_fixed_timestep(Some(STEP));
+        time
+    }
+
+    #[test]
+    fn fixed_timestep_accumulates_leftover_time() {
+        let mut time = fixed();
+        assert_eq!(frames(&mut time, &[4, 4, 4, 25]), [0, 0, 1, 2]);
+        assert_eq!(time.delta(), STEP);
+        assert_eq!(time.elapsed(), 3 * STEP);
+        // 37 ms in total, 7 of them left over.
+        assert!((time.alpha() - 0.7).abs() < 1e-4, "{}", time.alpha());
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut time = fixed();
+        assert_eq!(frames(&mut time, &[1000]), [25]);
+        assert_eq!(time.frame_delta(), MAX_FRAME_DELTA);
+        assert_eq!(time.alpha(), 0.0);
+    }
+
+    #[test]
+    fn variable_timestep_runs_one_update_per_frame() {
+        let mut time = Time::default();
+        assert_eq!(frames(&mut time, &[16, 0, 33]), [1, 1, 1]);
+        assert_eq!(time.delta(), Duration::from_millis(33));
+        assert_eq!(time.elapsed(), Duration::from_millis(49));
+        assert_eq!(time.alpha(), 1.0);
+    }
+
+    #[test]
+    fn changing_timestep_drops_leftover_time() {
+        let mut time = fixed();
+        frames(&mut time, &[15]);
+        time.set_fixed_timestep(Some(STEP));
+        assert_eq!(time.alpha(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "fixed timestep must not be zero")]
+    fn zero_timestep_panics() {
+        Time::default().set_fixed_timestep(Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,393 @@
+use cg8::{
+    camera::{Camera, Projection, Viewport},
+    core::{ClearColor, Context},
+    filter::{DepthFilter, Fog, MagFilter, RoundColor},
+    image::{Image, Tonemap},
+    renderer::{
+        ColoredPolygonRenderer, ColoredPolygons, ColoredVertex, ColoredVertices, Indices, Instance,
+        Instances,
+    },
+};
+use glam::{vec3, Affine3A, Mat4};
+use std::path::Path;
+
+const SIZE: u32 = 32;
+
+fn triangle(ctx: &Context, color: [f32; 4]) -> ColoredPolygons {
+    let vertex = |x: f32, y: f32| ColoredVertex {
+        pos: [x, y, 0.0, 1.0],
+        color,
+    };
+    ColoredPolygons::new(
+        ColoredVertices::new(
+            ctx,
+            vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(0.0, 1.0)],
+        ),
+        Indices::new(ctx, vec![0, 1, 2]),
+    )
+}
+
+fn at(ctx: &Context, z: f32) -> Instances {
+    Instances::new(
+        ctx,
+        vec![Instance {
+            mat: Mat4::from_translation(vec3(0.0, 0.0, z)).to_cols_array_2d(),
+        }],
+    )
+}
+
+fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+    assert!(
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 1e-2),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+fn camera(ctx: &Context) -> Camera {
+    Camera::new(
+        ctx,
+        Affine3A::IDENTITY,
+        Projection::perspective(1.0, 1.0, 0.1, 100.0),
+        SIZE,
+        SIZE,
+    )
+}
+
+/// Draws a triangle 5 units in front of `camera` over black.
+fn render_triangle(ctx: &Context, camera: &Camera, dst: &cg8::core::Texture) {
+    let renderer = ColoredPolygonRenderer::new(ctx);
+    let polygons = triangle(ctx, [0.3, 0.6, 0.9, 1.0]);
+    let instances = at(ctx, 5.0);
+    let mut frame = ctx.begin_frame();
+    ClearColor {
+        color: wgpu::Color::BLACK,
+    }
+    .render(&mut frame, dst);
+    renderer.render(&mut frame, dst, &polygons, &instances, camera);
+    frame.submit();
+}
+
+#[test]
+fn renders_polygons_and_filters_without_window() {
+    let ctx = Context::headless(SIZE, SIZE);
+    assert!(ctx.is_headless());
+    let round_color = RoundColor::new(&ctx);
+    let camera = camera(&ctx);
+    let scene = ctx.create_texture(SIZE, SIZE);
+    let dst = ctx.create_texture(SIZE, SIZE);
+
+    render_triangle(&ctx, &camera, &scene);
+    let mut frame = ctx.begin_frame();
+    round_color.render(&mut frame, &scene, &dst);
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    assert_eq!((image.width(), image.height()), (SIZE, SIZE));
+    // Rounded to sixteenths by the filter.
+    assert_close(image.get(SIZE / 2, SIZE / 2), [0.3125, 0.625, 0.875, 1.0]);
+    assert_close(image.get(0, 0), [0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn mag_filter_copies_to_the_offscreen_surface() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let mag_filter = MagFilter::with_format(&ctx, ctx.config().format);
+    let src = ctx.create_texture(SIZE / 4, SIZE / 4);
+
+    let mut frame = ctx.begin_frame();
+    ClearColor {
+        color: wgpu::Color {
+            r: 0.25,
+            g: 0.5,
+            b: 0.75,
+            a: 1.0,
+        },
+    }
+    .render(&mut frame, &src);
+    let surface = ctx.surface_texture().unwrap();
+    mag_filter.render(&mut frame, &src, surface.texture());
+    frame.submit();
+
+    let image = ctx.read_texture(surface.texture());
+    assert_close(image.get(SIZE - 1, SIZE - 1), [0.25, 0.5, 0.75, 1.0]);
+}
+
+/// Set `UPDATE_GOLDEN=1` to overwrite the golden image after an intended change.
+#[test]
+fn triangle_matches_golden_image() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let dst = ctx.create_texture(SIZE, SIZE);
+    render_triangle(&ctx, &camera(&ctx), &dst);
+    let image = ctx.read_texture(&dst);
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/triangle.png");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save_png(&path, Tonemap::Clamp).unwrap();
+    }
+    let golden = Image::load_png(&path).unwrap();
+    assert_eq!(
+        (golden.width(), golden.height()),
+        (image.width(), image.height())
+    );
+    // Allow a few rasterization differences along the edges between backends.
+    let mismatched = image
+        .pixels()
+        .iter()
+        .zip(golden.pixels())
+        .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| (a - b).abs() > 2e-2))
+        .count();
+    assert!(
+        mismatched <= image.pixels().len() / 50,
+        "{mismatched} pixels differ from {}",
+        path.display()
+    );
+}
+
+#[test]
+fn reads_back_camera_depth() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let camera = camera(&ctx);
+    let dst = ctx.create_texture(SIZE, SIZE);
+    render_triangle(&ctx, &camera, &dst);
+
+    let depth = match ctx.try_read_texture(camera.depth_texture()) {
+        Ok(depth) => depth,
+        // GL cannot copy depth textures; the error is what's being tested then.
+        Err(cg8::Error::UnreadableTexture { .. })
+            if !ctx
+                .adapter()
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::DEPTH_TEXTURE_AND_BUFFER_COPIES) =>
+        {
+            return
+        }
+        Err(err) => panic!("{err}"),
+    };
+    let center = depth.get(SIZE / 2, SIZE / 2)[0];
+    assert!(center > 0.0 && center < 1.0, "{center}");
+    assert_eq!(depth.get(0, 0)[0], 1.0);
+}
+
+#[test]
+fn reading_texture_without_copy_src_is_an_error() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let texture = ctx.create_texture_with_usage(
+        SIZE,
+        SIZE,
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    assert!(matches!(
+        ctx.try_read_texture(&texture),
+        Err(cg8::Error::UnreadableTexture { .. })
+    ));
+}
+
+#[test]
+fn culled_draws_in_one_frame_keep_their_own_instances() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let renderer = ColoredPolygonRenderer::new(&ctx);
+    let polygons = triangle(&ctx, [1.0, 0.0, 0.0, 1.0]);
+    // More than the initial capacity of the culled buffer in front of and behind
+    // the cameras, so that it has to grow for each draw in the frame.
+    let front_row = (0..100).map(|i| vec3(0.0, 0.0, 5.0 + i as f32 * 0.01));
+    let back_row = (0..100).map(|i| vec3(0.0, 0.0, -5.0 - i as f32 * 0.01));
+    let instances = Instances::new(
+        &ctx,
+        front_row
+            .chain(back_row)
+            .map(|pos| Instance {
+                mat: Mat4::from_translation(pos).to_cols_array_2d(),
+            })
+            .collect(),
+    );
+    let front = camera(&ctx);
+    let mut back = camera(&ctx);
+    back.transform = Affine3A::from_rotation_y(std::f32::consts::PI);
+    back.update_buffer(&ctx);
+    let front_dst = ctx.create_texture(SIZE, SIZE);
+    let back_dst = ctx.create_texture(SIZE, SIZE);
+
+    let mut frame = ctx.begin_frame();
+    for (camera, dst) in [(&front, &front_dst), (&back, &back_dst)] {
+        ClearColor {
+            color: wgpu::Color::BLACK,
+        }
+        .render(&mut frame, dst);
+        renderer.render(&mut frame, dst, &polygons, &instances, camera);
+    }
+    frame.submit();
+
+    for dst in [&front_dst, &back_dst] {
+        let image = ctx.read_texture(dst);
+        assert_close(image.get(SIZE / 2, SIZE / 2), [1.0, 0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn split_screen_cameras_keep_each_others_depth() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let renderer = ColoredPolygonRenderer::new(&ctx);
+    let near = triangle(&ctx, [1.0, 0.0, 0.0, 1.0]);
+    let far = triangle(&ctx, [0.0, 0.0, 1.0, 1.0]);
+    let mut left = camera(&ctx);
+    left.set_viewport(&ctx, Viewport::split(2, 0));
+    let mut right = camera(&ctx);
+    right.set_depth_buffer(&ctx, left.depth_buffer().clone());
+    right.set_viewport(&ctx, Viewport::split(2, 1));
+    let dst = ctx.create_texture(SIZE, SIZE);
+
+    let mut frame = ctx.begin_frame();
+    ClearColor {
+        color: wgpu::Color::BLACK,
+    }
+    .render(&mut frame, &dst);
+    renderer.render(&mut frame, &dst, &near, &at(&ctx, 5.0), &left);
+    renderer.render(&mut frame, &dst, &near, &at(&ctx, 5.0), &right);
+    // Drawn after the other camera, but still behind the first triangle.
+    renderer.render(&mut frame, &dst, &far, &at(&ctx, 10.0), &left);
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    assert_close(image.get(SIZE / 4, SIZE / 2), [1.0, 0.0, 0.0, 1.0]);
+    assert_close(image.get(SIZE * 3 / 4, SIZE / 2), [1.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn picture_in_picture_clears_only_its_viewport() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let renderer = ColoredPolygonRenderer::new(&ctx);
+    let main = camera(&ctx);
+    let mut minimap = camera(&ctx);
+    let corner = Viewport::new(0.75, 0.0, 0.25, 0.25);
+    minimap.set_viewport(&ctx, corner);
+    let dst = ctx.create_texture(SIZE, SIZE);
+
+    let mut frame = ctx.begin_frame();
+    ClearColor {
+        color: wgpu::Color::BLACK,
+    }
+    .render(&mut frame, &dst);
+    let big = Instances::new(
+        &ctx,
+        vec![Instance {
+            mat: Mat4::from_scale_rotation_translation(
+                vec3(10.0, 10.0, 1.0),
+                Default::default(),
+                vec3(0.0, 0.0, 5.0),
+            )
+            .to_cols_array_2d(),
+        }],
+    );
+    renderer.render(
+        &mut frame,
+        &dst,
+        &triangle(&ctx, [1.0, 0.0, 0.0, 1.0]),
+        &big,
+        &main,
+    );
+    ClearColor {
+        color: wgpu::Color {
+            r: 0.0,
+            g: 0.0,
+            b: 1.0,
+            a: 1.0,
+        },
+    }
+    .render_viewport(&mut frame, &dst, corner);
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    // The triangle covers the whole main view, and the minimap shows none of it.
+    assert_close(image.get(SIZE / 2, SIZE / 2), [1.0, 0.0, 0.0, 1.0]);
+    assert_close(image.get(SIZE - 1, 0), [0.0, 0.0, 1.0, 1.0]);
+    assert_close(image.get(SIZE * 3 / 4, SIZE / 4), [1.0, 0.0, 0.0, 1.0]);
+    assert_close(image.get(SIZE * 3 / 4 - 1, 0), [1.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn viewport_beyond_the_target_is_clamped() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let renderer = ColoredPolygonRenderer::new(&ctx);
+    let viewport = Viewport::new(0.5, -0.5, 1.0, 1.0);
+    let half = SIZE as f32 / 2.0;
+    assert_eq!(viewport.to_pixels(SIZE, SIZE), [half, 0.0, half, half]);
+    assert_eq!(
+        viewport.scissor(SIZE, SIZE),
+        [SIZE / 2, 0, SIZE / 2, SIZE / 2]
+    );
+    let mut camera = camera(&ctx);
+    camera.set_viewport(&ctx, viewport);
+    let dst = ctx.create_texture(SIZE, SIZE);
+
+    let mut frame = ctx.begin_frame();
+    ClearColor {
+        color: wgpu::Color::BLACK,
+    }
+    .render(&mut frame, &dst);
+    renderer.render(
+        &mut frame,
+        &dst,
+        &triangle(&ctx, [1.0, 0.0, 0.0, 1.0]),
+        &at(&ctx, 5.0),
+        &camera,
+    );
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    assert_close(image.get(SIZE * 3 / 4, SIZE / 4), [1.0, 0.0, 0.0, 1.0]);
+    assert_close(image.get(SIZE / 4, SIZE * 3 / 4), [0.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn fog_fades_to_its_color_with_distance() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let fog = Fog::new(&ctx, [0.0, 1.0, 0.0], 0.1);
+    let camera = camera(&ctx);
+    let scene = ctx.create_texture(SIZE, SIZE);
+    let dst = ctx.create_texture(SIZE, SIZE);
+    render_triangle(&ctx, &camera, &scene);
+
+    let mut frame = ctx.begin_frame();
+    fog.render(&mut frame, &camera, &scene, &dst);
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    // 5 units away at the center; nothing drawn at the corner, which is at the
+    // far plane and almost fully fogged.
+    let visibility = (-0.5f32).exp();
+    let mix = |fog: f32, color: f32| fog + (color - fog) * visibility;
+    assert_close(
+        image.get(SIZE / 2, SIZE / 2),
+        [mix(0.0, 0.3), mix(1.0, 0.6), mix(0.0, 0.9), 1.0],
+    );
+    assert_close(image.get(0, 0), [0.0, 1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn custom_depth_filter_reads_camera_depth() {
+    let ctx = Context::headless(SIZE, SIZE);
+    let filter = DepthFilter::new(
+        &ctx,
+        "@fragment
+        fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+            let view = view_position(in.pos.xy);
+            return vec4(view.z / view.w, textureSample(tex, samp, in.tex_coords).gb, 1.0);
+        }",
+    );
+    let camera = camera(&ctx);
+    let scene = ctx.create_texture(SIZE, SIZE);
+    let dst = ctx.create_texture(SIZE, SIZE);
+    render_triangle(&ctx, &camera, &scene);
+
+    let mut frame = ctx.begin_frame();
+    filter.render(&mut frame, &camera, &scene, &dst);
+    frame.submit();
+
+    let image = ctx.read_texture(&dst);
+    assert_close(image.get(SIZE / 2, SIZE / 2), [5.0, 0.6, 0.9, 1.0]);
+}
@@ -0,0 +1,62 @@
+use cg8::{
+    action::{ActionMap, Binding},
+    input::{Input, InputEvent, PhysicalKey},
+};
+use winit::event::VirtualKeyCode;
+
+fn press_w() -> Input {
+    let mut input = Input::default();
+    input.apply(&InputEvent::PhysicalKeyPressed(PhysicalKey::W));
+    input.apply(&InputEvent::KeyPressed(VirtualKeyCode::W));
+    input.push_layer("console");
+    input
+}
+
+#[test]
+fn consuming_a_virtual_key_hides_its_physical_key() {
+    let input = press_w();
+    input.layer("console").unwrap().consume(VirtualKeyCode::W);
+    assert!(!input.is_pressed(VirtualKeyCode::W));
+    assert!(!input.is_pressed_physical(PhysicalKey::W));
+}
+
+#[test]
+fn consuming_a_physical_key_hides_its_virtual_key() {
+    let input = press_w();
+    input
+        .layer("console")
+        .unwrap()
+        .consume_physical(PhysicalKey::W);
+    assert!(!input.is_pressed_physical(PhysicalKey::W));
+    assert!(!input.is_pressed(VirtualKeyCode::W));
+}
+
+#[test]
+fn unrelated_key_stays_visible() {
+    let mut input = press_w();
+    input.apply(&InputEvent::PhysicalKeyPressed(PhysicalKey::A));
+    input.apply(&InputEvent::KeyPressed(VirtualKeyCode::A));
+    input.layer("console").unwrap().consume(VirtualKeyCode::W);
+    assert!(input.is_pressed_physical(PhysicalKey::A));
+    assert!(input.is_pressed(VirtualKeyCode::A));
+}
+
+#[test]
+fn physical_keys_are_saved_by_name() {
+    let mut actions = ActionMap::new();
+    actions.bind("forward", Binding::Physical(PhysicalKey::W));
+    actions.bind("other", Binding::Physical(PhysicalKey(0x1234)));
+    let ron = actions.to_ron();
+    assert!(ron.contains("Physical(W)"), "{ron}");
+    assert!(ron.contains("Physical(Scancode(4660))"), "{ron}");
+
+    let loaded = ActionMap::from_ron(&ron).unwrap();
+    assert_eq!(
+        loaded.bindings("forward"),
+        [Binding::Physical(PhysicalKey::W)]
+    );
+    assert_eq!(
+        loaded.bindings("other"),
+        [Binding::Physical(PhysicalKey(0x1234))]
+    );
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use cg8::{
+    core::{App, Context},
+    input::{InputEvent, PhysicalKey, Preedit},
+    record::{InputPlayback, InputRecording, InputTick},
+};
+use glam::{vec2, Vec2};
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+/// What an app could observe in each update.
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    elapsed: Duration,
+    forward: bool,
+    clicked: bool,
+    cursor: Option<Vec2>,
+    modifiers: ModifiersState,
+    text: String,
+    preedit: Option<Preedit>,
+}
+
+#[derive(Default)]
+struct Probe {
+    snapshots: Vec<Snapshot>,
+}
+
+impl App for Probe {
+    fn update(&mut self, ctx: &Context) {
+        let input = ctx.input();
+        self.snapshots.push(Snapshot {
+            elapsed: ctx.time().elapsed(),
+            forward: input.is_pressed_physical(PhysicalKey::W),
+            clicked: input.is_mouse_just_pressed(MouseButton::Left),
+            cursor: input.cursor_position(),
+            modifiers: input.modifiers(),
+            text: input.text().to_owned(),
+            preedit: input.preedit().cloned(),
+        });
+    }
+
+    fn render(&mut self, _ctx: &Context, _alpha: f32) {}
+}
+
+fn recording() -> InputRecording {
+    let tick = |millis, events| InputTick {
+        delta: Duration::from_millis(millis),
+        events,
+    };
+    InputRecording {
+        ticks: vec![
+            tick(
+                16,
+                vec![
+                    InputEvent::ModifiersChanged(ModifiersState::SHIFT | ModifiersState::CTRL),
+                    InputEvent::PhysicalKeyPressed(PhysicalKey::W),
+                    InputEvent::KeyPressed(VirtualKeyCode::W),
+                    InputEvent::CursorMoved(Some(vec2(12.5, 40.0))),
+                ],
+            ),
+            tick(
+                17,
+                vec![
+                    InputEvent::MousePressed(MouseButton::Left),
+                    InputEvent::MouseReleased(MouseButton::Left),
+                    InputEvent::ImeEnabled,
+                    InputEvent::ImePreedit("かな".into(), Some((0, 3))),
+                ],
+            ),
+            tick(
+                33,
+                vec![
+                    InputEvent::ImeCommit("仮名".into()),
+                    InputEvent::Character('x'),
+                    InputEvent::CursorMoved(None),
+                    InputEvent::ModifiersChanged(ModifiersState::empty()),
+                ],
+            ),
+            tick(
+                16,
+                vec![
+                    InputEvent::PhysicalKeyReleased(PhysicalKey::W),
+                    InputEvent::KeyReleased(VirtualKeyCode::W),
+                ],
+            ),
+        ],
+    }
+}
+
+#[test]
+fn replaying_a_saved_recording_matches_the_original_run() {
+    let recording = recording();
+
+    // The original run, fed the events as they arrive from the window.
+    let mut ctx = Context::headless(64, 64);
+    let mut original = Probe::default();
+    for tick in &recording.ticks {
+        for event in &tick.events {
+            ctx.input_mut().apply(event);
+        }
+        ctx.step(&mut original, tick.delta);
+    }
+    let elapsed = ctx.time().elapsed();
+    // EGL fails to create a second context on a thread that still holds one.
+    drop(ctx);
+
+    let path = std::env::temp_dir().join(format!("cg8-recording-{}.ron", std::process::id()));
+    recording.save(&path).unwrap();
+    let loaded = InputRecording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, recording);
+
+    let mut ctx = Context::headless(64, 64);
+    let mut replayed = Probe::default();
+    let mut playback = InputPlayback::new(loaded);
+    while playback.step(&mut ctx, &mut replayed) {}
+    assert!(playback.is_finished());
+
+    assert_eq!(replayed.snapshots, original.snapshots);
+    assert_eq!(ctx.time().elapsed(), elapsed);
+    assert_eq!(elapsed, Duration::from_millis(82));
+    assert!(original.snapshots[1].clicked);
+}
+
+#[test]
+fn ticks_and_frames_are_counted_separately() {
+    let mut ctx = Context::headless(64, 64);
+    let mut probe = Probe::default();
+    ctx.step(&mut probe, Duration::from_millis(10));
+    ctx.step(&mut probe, Duration::from_millis(10));
+    ctx.begin_frame().submit();
+    assert_eq!(ctx.tick_count(), 2);
+    assert_eq!(ctx.frame_count(), 1);
+}